@@ -0,0 +1,261 @@
+use ndarray::{parallel::prelude::*, Array1, Array2, Array3, ArrayView2, Axis, ScalarOperand};
+
+use num::{
+    complex::Complex,
+    traits::{Float, FloatConst, NumAssign},
+};
+
+use std::{iter::Sum, ops::Mul};
+
+use rustfft::FftNum;
+
+use crate::{
+    error::LdsError, sample::Sample, spectrum::Spectrum, station::Station, weights::BeamWeights,
+};
+
+/// Accumulated covariance of the per-antenna coarse channels, shape (ncoarse_ch, nant, nant)
+#[derive(Debug, Clone)]
+pub struct Visibilities<T> {
+    pub sum: Array3<Complex<T>>,
+    pub nsamples: usize,
+}
+
+impl<T> Visibilities<T>
+where
+    T: Float + NumAssign + Send + Sync + std::fmt::Debug,
+{
+    pub fn new(ncoarse_ch: usize, nant: usize) -> Self {
+        Visibilities {
+            sum: Array3::from_elem(
+                (ncoarse_ch, nant, nant),
+                Complex::<T>::new(T::zero(), T::zero()),
+            ),
+            nsamples: 0,
+        }
+    }
+
+    /// `channelized` as returned by `Station::channelize`, (ncoarse_ch, ntime) for each antenna
+    pub fn accumulate(&mut self, channelized: &[Array2<Complex<T>>]) -> Result<(), LdsError> {
+        let nant = self.sum.len_of(Axis(1));
+        if channelized.is_empty() || channelized.len() != nant {
+            return Err(LdsError::WrongAntCount {
+                what: "channelized",
+                len: channelized.len(),
+                nant,
+            });
+        }
+        self.sum
+            .axis_iter_mut(Axis(0))
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(c, mut r)| {
+                for (i, xi) in channelized.iter().enumerate() {
+                    for (j, xj) in channelized.iter().enumerate() {
+                        r[(i, j)] += xi
+                            .row(c)
+                            .iter()
+                            .zip(xj.row(c).iter())
+                            .fold(Complex::<T>::new(T::zero(), T::zero()), |a, (&x, &y)| {
+                                a + x * y.conj()
+                            });
+                    }
+                }
+            });
+        self.nsamples += channelized[0].ncols();
+        Ok(())
+    }
+
+    pub fn mean(&self) -> Array3<Complex<T>> {
+        let n = T::from(self.nsamples).unwrap();
+        self.sum.mapv(|x| x / n)
+    }
+}
+
+/// Visibilities of point sources at `directions`, (azimuth, zenith, flux, spectrum) with
+/// angles in radians as listed by `SkyModelSrc`, shape (ncoarse_ch, nant, nant); the flux
+/// of each channel follows the spectrum, which needs the sky frequency unless all are flat
+pub fn model_visibilities<R, T>(
+    station: &Station<R, T>,
+    directions: &[(T, T, T, Spectrum)],
+) -> Result<Array3<Complex<T>>, LdsError>
+where
+    T: Float
+        + ScalarOperand
+        + FloatConst
+        + NumAssign
+        + std::iter::Sum
+        + std::marker::Send
+        + std::marker::Sync
+        + FftNum
+        + Default
+        + std::fmt::Debug,
+    Complex<T>: Copy + std::convert::From<R> + Sum + Default + ScalarOperand,
+    R: Copy
+        + Mul<T, Output = R>
+        + Default
+        + ScalarOperand
+        + NumAssign
+        + std::fmt::Debug
+        + Sum
+        + Sync
+        + Send
+        + Sample<T>,
+{
+    let nant = station.ants.len();
+    let ncoarse_ch = station.ncoarse_ch();
+    let ch: Vec<_> = (0..ncoarse_ch).collect();
+    let freqs = station.coarse_ch_freq_in_fs(&ch);
+    let freqs_in_hz = if directions
+        .iter()
+        .all(|(.., spectrum)| matches!(spectrum, Spectrum::Flat))
+    {
+        vec![T::zero(); ncoarse_ch]
+    } else {
+        station.coarse_ch_freq_in_hz(&ch)?
+    };
+    let delays: Vec<_> = directions
+        .iter()
+        .map(|&(az, ze, ..)| station.calc_required_digital_delay(az, ze))
+        .collect();
+    // (ncoarse_ch, ndirections)
+    let fluxes = Array2::from_shape_fn((ncoarse_ch, directions.len()), |(c, k)| {
+        let (_, _, flux, spectrum) = &directions[k];
+        *flux * T::from(spectrum.psd(freqs_in_hz[c].to_f64().unwrap())).unwrap()
+    });
+    let two = T::one() + T::one();
+    Ok(Array3::from_shape_fn(
+        (ncoarse_ch, nant, nant),
+        |(c, i, j)| {
+            delays
+                .iter()
+                .zip(fluxes.row(c).iter())
+                .map(|(d, &flux)| {
+                    Complex::<T>::new(T::zero(), two * T::PI() * freqs[c] * (d[i] - d[j])).exp()
+                        * flux
+                })
+                .sum::<Complex<T>>()
+        },
+    ))
+}
+
+/// StEFCal (Salvini & Wijnholds 2014) on one channel, ignoring the autocorrelations;
+/// returns `g` with `r = diag(g) m diag(g)^H`, the number of iterations and whether
+/// the relative update fell below `tol`
+pub fn stefcal<T>(
+    r: ArrayView2<Complex<T>>,
+    m: ArrayView2<Complex<T>>,
+    max_iter: usize,
+    tol: T,
+) -> (Array1<Complex<T>>, usize, bool)
+where
+    T: Float + NumAssign + std::fmt::Debug,
+{
+    let n = r.nrows();
+    let zero = Complex::<T>::new(T::zero(), T::zero());
+    let half = T::from(0.5).unwrap();
+    let norm = |g: &Array1<Complex<T>>| g.iter().fold(T::zero(), |a, x| a + x.norm_sqr()).sqrt();
+
+    let mut g = Array1::from_elem(n, Complex::<T>::new(T::one(), T::zero()));
+    for iter in 1..=max_iter {
+        let g_old = g.clone();
+        for p in 0..n {
+            let mut num = zero;
+            let mut den = T::zero();
+            for i in (0..n).filter(|&i| i != p) {
+                let z = g_old[i] * m[(i, p)];
+                num += r[(i, p)].conj() * z;
+                den += z.norm_sqr();
+            }
+            g[p] = if den > T::zero() { num / den } else { zero };
+        }
+        // averaging every other iteration stops the odd/even oscillation
+        if iter % 2 == 0 {
+            let diff = norm(&(&g - &g_old));
+            let g_norm = norm(&g);
+            g = (&g + &g_old).mapv(|x| x * half);
+            if diff <= tol * g_norm {
+                return (g, iter, true);
+            }
+        }
+    }
+    (g, max_iter, false)
+}
+
+/// Per-antenna gains of every coarse channel, shape (nant, ncoarse_ch), with the phase
+/// of antenna 0 as reference
+#[derive(Debug, Clone)]
+pub struct GainSolution<T> {
+    pub gains: Array2<Complex<T>>,
+    pub niter: Vec<usize>,
+    pub converged: Vec<bool>,
+}
+
+impl<T> GainSolution<T>
+where
+    T: Float + FloatConst + NumAssign + Send + Sync + std::fmt::Debug,
+{
+    /// `vis` and `model` with shape (ncoarse_ch, nant, nant)
+    pub fn solve(
+        vis: &Array3<Complex<T>>,
+        model: &Array3<Complex<T>>,
+        max_iter: usize,
+        tol: T,
+    ) -> Result<Self, LdsError> {
+        if vis.shape() != model.shape() {
+            return Err(LdsError::VisShapeMismatch {
+                vis: vis.shape().to_vec(),
+                model: model.shape().to_vec(),
+            });
+        }
+        let solutions: Vec<_> = vis
+            .axis_iter(Axis(0))
+            .into_par_iter()
+            .zip(model.axis_iter(Axis(0)).into_par_iter())
+            .map(|(r, m)| {
+                let (mut g, niter, converged) = stefcal(r, m, max_iter, tol);
+                let g0 = g[0];
+                if g0.norm() > T::zero() {
+                    let rot = g0.conj() / g0.norm();
+                    g.mapv_inplace(|x| x * rot);
+                }
+                (g, niter, converged)
+            })
+            .collect();
+
+        let (nchan, nant) = (vis.len_of(Axis(0)), vis.len_of(Axis(1)));
+        Ok(GainSolution {
+            gains: Array2::from_shape_fn((nant, nchan), |(a, c)| solutions[c].0[a]),
+            niter: solutions.iter().map(|s| s.1).collect(),
+            converged: solutions.iter().map(|s| s.2).collect(),
+        })
+    }
+
+    /// Inverse gains, zero for antennas without a solution, see `BeamWeights::with_gains`
+    pub fn corrections(&self) -> Array2<Complex<T>> {
+        self.gains.mapv(|g| {
+            if g.norm_sqr() > T::zero() {
+                g.inv()
+            } else {
+                Complex::<T>::new(T::zero(), T::zero())
+            }
+        })
+    }
+
+    pub fn weights(&self, weights: BeamWeights<T>) -> BeamWeights<T> {
+        weights.with_gains(self.corrections().view())
+    }
+
+    /// Gains `errors` leaves after correction, scaled to unit mean over the antennas of
+    /// each channel so that a perfect calibration gives all ones
+    pub fn residual(&self, errors: &BeamWeights<T>) -> Array2<Complex<T>> {
+        let mut residual = &errors.w * &self.corrections();
+        let n = T::from(residual.nrows()).unwrap();
+        for mut col in residual.columns_mut() {
+            let mean = col.sum() / n;
+            if mean.norm_sqr() > T::zero() {
+                col.mapv_inplace(|x| x / mean);
+            }
+        }
+        residual
+    }
+}
@@ -1,5 +1,14 @@
+use std::path::{Path, PathBuf};
+
+use num::traits::Float;
+
 use rsdsp::cfg::{DelayerCfg, PfbCfg};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    coords::{lst, radec2azze},
+    error::LdsError,
+};
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct StationCfg {
@@ -9,14 +18,599 @@ pub struct StationCfg {
     pub delayer: DelayerCfg,
     pub selected_coarse_ch: Vec<(usize, usize)>,
     pub fine_pfb: PfbCfg,
+    #[serde(default)]
+    pub sky_freq: Option<SkyFreqCfg>,
+    #[serde(default)]
+    pub beams: Vec<BeamCfg>,
+    #[serde(default)]
+    pub location: Option<LocationCfg>,
+    #[serde(default)]
+    pub digitizer: Option<DigitizerCfg>,
+    #[serde(default)]
+    pub requant: RequantStagesCfg,
+    #[serde(default)]
+    pub ant_errors: Option<AntErrorCfg>,
+    /// observation time at which RA/Dec beams are pointed
+    #[serde(default)]
+    pub mjd: Option<f64>,
+}
+
+/// Per-antenna errors applied to the coarse channels of every source, delays in samples
+/// and phases in degrees, an empty list leaves that error out. This is a narrowband model:
+/// the errors act as one complex gain per coarse channel after the coarse PFB and the
+/// digitizer, so a delay is only its phase at the channel centre and the ADC sees the
+/// signal without gain errors
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct AntErrorCfg {
+    #[serde(default)]
+    pub delay: Vec<f64>,
+    #[serde(default)]
+    pub gain: Vec<f64>,
+    #[serde(default)]
+    pub phase: Vec<f64>,
+    /// amplitude of each coarse channel, one list per antenna
+    #[serde(default)]
+    pub bandpass: Vec<Vec<f64>>,
+    #[serde(default)]
+    pub random: Option<RandomAntErrorCfg>,
+}
+
+/// Normal errors added on top of the listed ones, `gain_sigma` and `bandpass_sigma` are relative
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub struct RandomAntErrorCfg {
+    pub seed: u64,
+    #[serde(default)]
+    pub delay_sigma: f64,
+    #[serde(default)]
+    pub gain_sigma: f64,
+    #[serde(default)]
+    pub phase_sigma: f64,
+    #[serde(default)]
+    pub bandpass_sigma: f64,
+}
+
+/// ADC applied to every real component, an input of RMS `rms` spans `rms_lsb` steps
+/// and the output saturates symmetrically at `2^(nbits-1) - 1` steps
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub struct DigitizerCfg {
+    #[serde(default = "default_nbits")]
+    pub nbits: u32,
+    pub rms: f64,
+    #[serde(default = "default_rms_lsb")]
+    pub rms_lsb: f64,
+}
+
+fn default_nbits() -> u32 {
+    8
+}
+
+fn default_rms_lsb() -> f64 {
+    16.0
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// half away from zero
+    #[default]
+    Nearest,
+    /// half to even
+    Even,
+    Floor,
+    Zero,
+}
+
+/// Requantization to `nbits` per real component with quantization step `lsb`,
+/// after multiplying channel `c` by `gain[c]` (unity if `gain` is empty)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct RequantCfg {
+    pub nbits: u32,
+    #[serde(default = "default_lsb")]
+    pub lsb: f64,
+    #[serde(default)]
+    pub gain: Vec<f64>,
+    #[serde(default)]
+    pub rounding: Rounding,
+}
+
+fn default_lsb() -> f64 {
+    1.0
+}
+
+/// The coarse and beam gains are per coarse channel, the fine ones per selected fine
+/// channel; configured beams take their fine gains from `BeamCfg::fine_gain`
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct RequantStagesCfg {
+    #[serde(default)]
+    pub coarse: Option<RequantCfg>,
+    #[serde(default)]
+    pub beam: Option<RequantCfg>,
+    #[serde(default)]
+    pub fine: Option<RequantCfg>,
+}
+
+/// Geodetic station location in degrees, longitude positive east
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub struct LocationCfg {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// A station beam with its own share of the coarse channels, angles in degrees
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct BeamCfg {
+    #[serde(flatten)]
+    pub pos: SkyPos,
+    pub selected_coarse_ch: Vec<(usize, usize)>,
+    /// fine requantization gain per fine channel of this beam, unity if empty
+    #[serde(default)]
+    pub fine_gain: Vec<f64>,
+}
+
+impl BeamCfg {
+    pub fn coarse_ch_selected(&self) -> Vec<usize> {
+        flatten_ranges(&self.selected_coarse_ch)
+    }
+}
+
+/// Receiver noise per antenna plus correlated sky noise from `pos` (degrees)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct NoiseCfg {
+    pub seed: u64,
+    pub receiver_sigma: f64,
+    pub sky_sigma: f64,
+    #[serde(flatten)]
+    pub pos: SkyPos,
+    #[serde(default)]
+    pub mjd: Option<f64>,
+}
+
+/// Power spectral density over sky frequency in Hz
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpectrumCfg {
+    Flat,
+    PowerLaw {
+        index: f64,
+        f_ref: f64,
+    },
+    /// two whitespace separated columns: frequency in Hz and psd
+    Tabulated {
+        file: PathBuf,
+    },
+}
+
+/// Stationary random signal with the given spectrum from `pos` (degrees)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct BroadbandCfg {
+    pub seed: u64,
+    pub spectrum: SpectrumCfg,
+    #[serde(flatten)]
+    pub pos: SkyPos,
+    #[serde(default)]
+    pub mjd: Option<f64>,
+    #[serde(default = "default_spectrum_ntap")]
+    pub ntap: usize,
+}
+
+fn default_spectrum_ntap() -> usize {
+    256
+}
+
+/// Point sources and optional diffuse emission; angles in degrees, fluxes at `f_ref` Hz.
+/// RA/Dec sources use `observer`, or else the station location at `mjd`
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SkyModelCfg {
+    pub seed: u64,
+    #[serde(default)]
+    pub f_ref: f64,
+    #[serde(default)]
+    pub observer: Option<ObserverCfg>,
+    #[serde(default)]
+    pub mjd: Option<f64>,
+    #[serde(default)]
+    pub sources: Vec<PointSrcCfg>,
+    #[serde(default)]
+    pub diffuse: Option<DiffuseCfg>,
+    #[serde(default = "default_spectrum_ntap")]
+    pub ntap: usize,
+}
+
+/// Needed to place RA/Dec sources, in degrees
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ObserverCfg {
+    pub latitude: f64,
+    pub lst: f64,
+}
+
+/// Pointing in degrees, either local or equatorial; a missing `az` or `ze` is 0
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+#[serde(untagged)]
+pub enum SkyPos {
+    RaDec {
+        ra: f64,
+        dec: f64,
+    },
+    AzZe {
+        #[serde(default)]
+        az: f64,
+        #[serde(default)]
+        ze: f64,
+    },
+}
+
+impl SkyPos {
+    /// (azimuth, zenith) in radians, RA/Dec seen from `location` at `mjd`
+    pub fn azze(
+        &self,
+        location: Option<&LocationCfg>,
+        mjd: Option<f64>,
+    ) -> Result<(f64, f64), LdsError> {
+        match *self {
+            SkyPos::AzZe { az, ze } => Ok((az.to_radians(), ze.to_radians())),
+            SkyPos::RaDec { ra, dec } => {
+                let location = location.ok_or(LdsError::NoLocation)?;
+                let mjd = mjd.ok_or(LdsError::NoMjd)?;
+                Ok(radec2azze(
+                    ra.to_radians(),
+                    dec.to_radians(),
+                    lst(mjd, location.longitude.to_radians()),
+                    location.latitude.to_radians(),
+                ))
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PointSrcCfg {
+    #[serde(flatten)]
+    pub pos: SkyPos,
+    pub flux: f64,
+    #[serde(default)]
+    pub spectral_index: f64,
+}
+
+/// Approximated by `nsrc` incoherent sources spread uniformly over the visible sky
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct DiffuseCfg {
+    pub total_flux: f64,
+    pub nsrc: usize,
+    #[serde(default)]
+    pub spectral_index: f64,
+}
+
+/// Periodic (or single, without `period`) dispersed Gaussian pulse from `pos`
+/// (degrees); `width` is the FWHM and `t0` the start of the first pulse at the top
+/// of the band, both in seconds, `dm` in pc cm^-3
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PulsarCfg {
+    #[serde(flatten)]
+    pub pos: SkyPos,
+    #[serde(default)]
+    pub mjd: Option<f64>,
+    pub dm: f64,
+    pub width: f64,
+    #[serde(default)]
+    pub period: Option<f64>,
+    #[serde(default)]
+    pub t0: f64,
+    #[serde(default = "default_pulse_ampl")]
+    pub ampl: f64,
+    /// sky frequencies below this (Hz) carry no pulse, bounding the sweep length
+    #[serde(default)]
+    pub f_min: f64,
+}
+
+fn default_pulse_ampl() -> f64 {
+    1.0
+}
+
+/// Radio frequency interference; angles in degrees, `freq` in units of the
+/// sampling rate, times in seconds, `mjd` places an RA/Dec `pos`
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RfiCfg {
+    /// tone whose frequency drifts by `drift` (in units of the sampling rate) per second
+    Chirp {
+        #[serde(flatten)]
+        pos: SkyPos,
+        #[serde(default)]
+        mjd: Option<f64>,
+        freq: f64,
+        #[serde(default)]
+        drift: f64,
+        #[serde(default = "default_pulse_ampl")]
+        ampl: f64,
+    },
+    /// broadband noise bursts of length `width`, arriving at `rate` per second
+    Impulsive {
+        seed: u64,
+        #[serde(flatten)]
+        pos: SkyPos,
+        #[serde(default)]
+        mjd: Option<f64>,
+        rate: f64,
+        width: f64,
+        #[serde(default = "default_pulse_ampl")]
+        ampl: f64,
+    },
+    /// tone from an emitter moving from `pos` by `az_rate`, `ze_rate` degrees per second
+    Satellite {
+        #[serde(flatten)]
+        pos: SkyPos,
+        #[serde(default)]
+        mjd: Option<f64>,
+        az_rate: f64,
+        ze_rate: f64,
+        freq: f64,
+        #[serde(default = "default_pulse_ampl")]
+        ampl: f64,
+    },
+}
+
+fn flatten_ranges(ranges: &[(usize, usize)]) -> Vec<usize> {
+    ranges.iter().flat_map(|&(cb, ce)| cb..ce).collect()
+}
+
+fn validate_coarse_ch(ranges: &[(usize, usize)], nch: usize, issues: &mut Vec<LdsError>) {
+    if ranges.is_empty() {
+        issues.push(LdsError::NoCoarseChSelected);
+    }
+    for (i, &r1) in ranges.iter().enumerate() {
+        if r1.0 >= r1.1 {
+            issues.push(LdsError::ReversedCoarseCh(r1));
+            continue;
+        }
+        if r1.1 > nch {
+            issues.push(LdsError::CoarseChOutOfRange { range: r1, nch });
+        }
+        for &r2 in ranges.iter().skip(i + 1) {
+            if r2.0 < r2.1 && r1.0 < r2.1 && r2.0 < r1.1 {
+                issues.push(LdsError::OverlappingCoarseCh(r1, r2));
+            }
+        }
+    }
+}
+
+/// Maps frequencies in units of the sampling rate `1/dt` onto the sky.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SkyFreqCfg {
+    /// 1-based; even zones are spectrally inverted
+    #[serde(default = "default_nyquist_zone")]
+    pub nyquist_zone: usize,
+    /// LO frequency in Hz added after the zone mapping
+    #[serde(default)]
+    pub lo_freq: f64,
+}
+
+fn default_nyquist_zone() -> usize {
+    1
+}
+
+impl SkyFreqCfg {
+    pub fn inverted(&self) -> bool {
+        self.nyquist_zone.is_multiple_of(2)
+    }
+
+    pub fn freq_in_hz<T: Float>(&self, f_in_fs: T, dt: T) -> T {
+        let fs = T::one() / dt;
+        let zone = T::from(self.nyquist_zone).unwrap();
+        let two = T::one() + T::one();
+        let f = if !self.inverted() {
+            (zone - T::one()) / two + f_in_fs
+        } else {
+            zone / two - f_in_fs
+        };
+        T::from(self.lo_freq).unwrap() + f * fs
+    }
+}
+
+pub fn load_yaml<V: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<V, LdsError> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path).map_err(|source| LdsError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    serde_yaml::from_reader(file).map_err(|source| LdsError::Yaml {
+        path: path.to_path_buf(),
+        source,
+    })
 }
 
 impl StationCfg {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LdsError> {
+        load_yaml(path)
+    }
+
+    pub fn validate(&self) -> Result<(), LdsError> {
+        let mut issues = Vec::new();
+
+        if !self.dt.is_finite() || self.dt <= 0.0 {
+            issues.push(LdsError::NonPositiveDt(self.dt));
+        }
+
+        if self.pos.is_empty() {
+            issues.push(LdsError::EmptyPos);
+        }
+        for (i, p1) in self.pos.iter().enumerate() {
+            for (j, p2) in self.pos.iter().enumerate().skip(i + 1) {
+                if p1 == p2 {
+                    issues.push(LdsError::DuplicatePos(i, j));
+                }
+            }
+        }
+
+        if self.coarse_pfb.nch == 0 || !self.coarse_pfb.nch.is_multiple_of(2) {
+            issues.push(LdsError::InvalidPfbGeometry {
+                stage: "coarse",
+                reason: format!(
+                    "nch = {} is not a positive even number",
+                    self.coarse_pfb.nch
+                ),
+            });
+        }
+        if self.fine_pfb.nch == 0 {
+            issues.push(LdsError::InvalidPfbGeometry {
+                stage: "fine",
+                reason: "nch = 0".to_string(),
+            });
+        }
+        for (stage, pfb) in [("coarse", &self.coarse_pfb), ("fine", &self.fine_pfb)] {
+            if pfb.tap_per_ch == 0 {
+                issues.push(LdsError::InvalidPfbGeometry {
+                    stage,
+                    reason: "tap_per_ch = 0".to_string(),
+                });
+            }
+        }
+
+        if let Some(location) = &self.location {
+            if !(-90.0..=90.0).contains(&location.latitude) {
+                issues.push(LdsError::InvalidLatitude(location.latitude));
+            }
+        }
+
+        if let Some(digitizer) = &self.digitizer {
+            if !(2..=32).contains(&digitizer.nbits) {
+                issues.push(LdsError::InvalidDigitizer(format!(
+                    "nbits = {} is outside [2, 32]",
+                    digitizer.nbits
+                )));
+            }
+            if digitizer.rms.is_nan() || digitizer.rms <= 0.0 {
+                issues.push(LdsError::InvalidDigitizer(format!(
+                    "rms = {} is not positive",
+                    digitizer.rms
+                )));
+            }
+            if digitizer.rms_lsb.is_nan() || digitizer.rms_lsb <= 0.0 {
+                issues.push(LdsError::InvalidDigitizer(format!(
+                    "rms_lsb = {} is not positive",
+                    digitizer.rms_lsb
+                )));
+            }
+        }
+
+        if let Some(ant_errors) = &self.ant_errors {
+            let nant = self.pos.len();
+            for (name, n) in [
+                ("delay", ant_errors.delay.len()),
+                ("gain", ant_errors.gain.len()),
+                ("phase", ant_errors.phase.len()),
+                ("bandpass", ant_errors.bandpass.len()),
+            ] {
+                if n != 0 && n != nant {
+                    issues.push(LdsError::InvalidAntErrors(format!(
+                        "{} {} values for {} antennas",
+                        n, name, nant
+                    )));
+                }
+            }
+            for (i, bp) in ant_errors.bandpass.iter().enumerate() {
+                if bp.len() != self.coarse_pfb.nch {
+                    issues.push(LdsError::InvalidAntErrors(format!(
+                        "antenna {} has {} bandpass values for {} channels",
+                        i,
+                        bp.len(),
+                        self.coarse_pfb.nch
+                    )));
+                }
+            }
+            if let Some(random) = &ant_errors.random {
+                for (name, sigma) in [
+                    ("delay_sigma", random.delay_sigma),
+                    ("gain_sigma", random.gain_sigma),
+                    ("phase_sigma", random.phase_sigma),
+                    ("bandpass_sigma", random.bandpass_sigma),
+                ] {
+                    if !sigma.is_finite() || sigma < 0.0 {
+                        issues.push(LdsError::InvalidAntErrors(format!(
+                            "{} = {} is negative or not finite",
+                            name, sigma
+                        )));
+                    }
+                }
+            }
+        }
+
+        for (stage, requant, nch) in [
+            ("coarse", &self.requant.coarse, self.coarse_pfb.nch),
+            ("beam", &self.requant.beam, self.coarse_pfb.nch),
+            ("fine", &self.requant.fine, self.total_nfine_ch()),
+        ] {
+            let Some(requant) = requant else {
+                continue;
+            };
+            if !(2..=32).contains(&requant.nbits) {
+                issues.push(LdsError::InvalidRequant {
+                    stage,
+                    reason: format!("nbits = {} is outside [2, 32]", requant.nbits),
+                });
+            }
+            if requant.lsb.is_nan() || requant.lsb <= 0.0 {
+                issues.push(LdsError::InvalidRequant {
+                    stage,
+                    reason: format!("lsb = {} is not positive", requant.lsb),
+                });
+            }
+            if !requant.gain.is_empty() && requant.gain.len() != nch {
+                issues.push(LdsError::InvalidRequant {
+                    stage,
+                    reason: format!("{} gains for {} channels", requant.gain.len(), nch),
+                });
+            }
+        }
+        for (i, beam) in self.beams.iter().enumerate() {
+            let nch = self.fine_pfb.nch * beam.coarse_ch_selected().len();
+            if !beam.fine_gain.is_empty() && beam.fine_gain.len() != nch {
+                issues.push(LdsError::InvalidBeam {
+                    beam: i,
+                    issue: Box::new(LdsError::InvalidRequant {
+                        stage: "fine",
+                        reason: format!("{} gains for {} channels", beam.fine_gain.len(), nch),
+                    }),
+                });
+            }
+        }
+
+        if let Some(sky_freq) = &self.sky_freq {
+            if sky_freq.nyquist_zone == 0 {
+                issues.push(LdsError::InvalidNyquistZone);
+            }
+        }
+
+        validate_coarse_ch(&self.selected_coarse_ch, self.coarse_pfb.nch, &mut issues);
+
+        for (i, beam) in self.beams.iter().enumerate() {
+            let mut beam_issues = Vec::new();
+            validate_coarse_ch(
+                &beam.selected_coarse_ch,
+                self.coarse_pfb.nch,
+                &mut beam_issues,
+            );
+            issues.extend(beam_issues.into_iter().map(|e| LdsError::InvalidBeam {
+                beam: i,
+                issue: Box::new(e),
+            }));
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(LdsError::InvalidCfg(issues))
+        }
+    }
+
+    pub fn coarse_ch_selected(&self) -> Vec<usize> {
+        flatten_ranges(&self.selected_coarse_ch)
+    }
+
     pub fn nselected_coarse_ch(&self) -> usize {
         self.selected_coarse_ch
             .iter()
-            .map(|&(cb, ce)| ce - cb)
-            .product()
+            .map(|&(cb, ce)| ce.saturating_sub(cb))
+            .sum()
     }
 
     pub fn total_nfine_ch(&self) -> usize {
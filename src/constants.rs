@@ -3,3 +3,13 @@ use num::traits::Float;
 pub fn light_speed<T: Float>() -> T {
     T::from(2.99792458e8).unwrap()
 }
+
+/// in s Hz^2 / (pc cm^-3)
+pub fn dispersion_const<T: Float>() -> T {
+    T::from(4.148808e15).unwrap()
+}
+
+/// sidereal, in rad/s
+pub fn earth_rotation_rate<T: Float>() -> T {
+    T::from(7.2921150e-5).unwrap()
+}
@@ -0,0 +1,59 @@
+use num::traits::{Float, FloatConst};
+
+/// MJD of the J2000.0 epoch
+pub const MJD_J2000: f64 = 51544.5;
+
+/// MJD of 1970-01-01T00:00:00 UTC
+pub const MJD_UNIX_EPOCH: f64 = 40587.0;
+
+pub fn unix_to_mjd(secs: f64) -> f64 {
+    secs / 86400.0 + MJD_UNIX_EPOCH
+}
+
+/// Greenwich mean sidereal time in radians, UT1 taken as UTC
+pub fn gmst(mjd: f64) -> f64 {
+    let d = mjd - MJD_J2000;
+    let t = d / 36525.0;
+    let deg = 280.46061837 + 360.98564736629 * d + 0.000387933 * t * t - t * t * t / 38710000.0;
+    deg.rem_euclid(360.0).to_radians()
+}
+
+/// Local mean sidereal time in radians, `longitude` in radians positive east
+pub fn lst(mjd: f64, longitude: f64) -> f64 {
+    (gmst(mjd) + longitude).rem_euclid(2.0 * std::f64::consts::PI)
+}
+
+/// Local hour angle `lst - ra`, returns (azimuth, zenith), all in radians
+pub fn radec2azze<T>(ra: T, dec: T, lst: T, latitude: T) -> (T, T)
+where
+    T: Float + FloatConst,
+{
+    let h = lst - ra;
+    let sin_alt = dec.sin() * latitude.sin() + dec.cos() * latitude.cos() * h.cos();
+    let az = (-dec.cos() * h.sin())
+        .atan2(dec.sin() * latitude.cos() - dec.cos() * latitude.sin() * h.cos());
+    let az = if az < T::zero() {
+        az + T::PI() + T::PI()
+    } else {
+        az
+    };
+    (
+        az,
+        T::FRAC_PI_2() - sin_alt.max(-T::one()).min(T::one()).asin(),
+    )
+}
+
+/// Inverse of `radec2azze`, returns (ra, dec) in radians
+pub fn azze2radec<T>(az: T, ze: T, lst: T, latitude: T) -> (T, T)
+where
+    T: Float + FloatConst,
+{
+    let alt = T::FRAC_PI_2() - ze;
+    let sin_dec = alt.sin() * latitude.sin() + alt.cos() * latitude.cos() * az.cos();
+    let h = (-az.sin() * alt.cos())
+        .atan2(alt.sin() * latitude.cos() - alt.cos() * latitude.sin() * az.cos());
+    let two_pi = T::PI() + T::PI();
+    let ra = lst - h;
+    let ra = ra - (ra / two_pi).floor() * two_pi;
+    (ra, sin_dec.max(-T::one()).min(T::one()).asin())
+}
@@ -0,0 +1,109 @@
+use num::traits::Float;
+
+use crate::{cfg::DigitizerCfg, sample::Sample};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Digitizer<T> {
+    pub lsb: T,
+    /// saturation in units of `lsb`
+    pub max_level: T,
+}
+
+/// Counts of one antenna's block, see `Digitizer::digitize`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DigitizedBlock {
+    pub nsamples: usize,
+    pub nclipped: usize,
+    pub signal_power: f64,
+    pub error_power: f64,
+}
+
+/// Accumulated per antenna, counting real components
+#[derive(Debug, Clone, Default)]
+pub struct DigitizerStats {
+    pub nsamples: Vec<usize>,
+    pub nclipped: Vec<usize>,
+    pub signal_power: Vec<f64>,
+    pub error_power: Vec<f64>,
+}
+
+impl DigitizerStats {
+    pub fn new(nant: usize) -> Self {
+        DigitizerStats {
+            nsamples: vec![0; nant],
+            nclipped: vec![0; nant],
+            signal_power: vec![0.0; nant],
+            error_power: vec![0.0; nant],
+        }
+    }
+
+    pub fn add(&mut self, ant: usize, block: &DigitizedBlock) {
+        self.nsamples[ant] += block.nsamples;
+        self.nclipped[ant] += block.nclipped;
+        self.signal_power[ant] += block.signal_power;
+        self.error_power[ant] += block.error_power;
+    }
+
+    pub fn clip_fraction(&self) -> Vec<f64> {
+        self.nclipped
+            .iter()
+            .zip(self.nsamples.iter())
+            .map(|(&c, &n)| c as f64 / n.max(1) as f64)
+            .collect()
+    }
+
+    /// Signal to quantization (and clipping) error power ratio
+    pub fn sqnr(&self) -> Vec<f64> {
+        self.signal_power
+            .iter()
+            .zip(self.error_power.iter())
+            .map(|(&s, &e)| s / e)
+            .collect()
+    }
+}
+
+impl<T> Digitizer<T>
+where
+    T: Float,
+{
+    pub fn new(nbits: u32, lsb: T) -> Self {
+        Digitizer {
+            lsb,
+            max_level: T::from((1_u64 << (nbits - 1)) - 1).unwrap(),
+        }
+    }
+
+    pub fn from_cfg(cfg: &DigitizerCfg) -> Self {
+        Self::new(cfg.nbits, T::from(cfg.rms / cfg.rms_lsb).unwrap())
+    }
+
+    /// Returns the quantized value and whether it saturated
+    pub fn quantize(&self, x: T) -> (T, bool) {
+        let level = (x / self.lsb).round();
+        if level.abs() > self.max_level {
+            (self.max_level.copysign(level) * self.lsb, true)
+        } else {
+            (level * self.lsb, false)
+        }
+    }
+
+    /// Quantizes every real component of `s` in place
+    pub fn digitize<R: Sample<T>>(&self, s: &mut [R]) -> DigitizedBlock {
+        let mut block = DigitizedBlock {
+            nsamples: s.len() * if R::IS_REAL { 1 } else { 2 },
+            ..Default::default()
+        };
+        for x in s.iter_mut() {
+            *x = x.map_parts(|x1| {
+                let (q, clipped) = self.quantize(x1);
+                block.nclipped += clipped as usize;
+                let x1 = x1.to_f64().unwrap();
+                let e = x1 - q.to_f64().unwrap();
+                block.signal_power += x1 * x1;
+                block.error_power += e * e;
+                q
+            });
+        }
+        block
+    }
+}
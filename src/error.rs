@@ -0,0 +1,171 @@
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug)]
+pub enum LdsError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Yaml {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+    InvalidPfbGeometry {
+        stage: &'static str,
+        reason: String,
+    },
+    EmptyPos,
+    CoarseChOutOfRange {
+        range: (usize, usize),
+        nch: usize,
+    },
+    ReversedCoarseCh((usize, usize)),
+    OverlappingCoarseCh((usize, usize), (usize, usize)),
+    NoCoarseChSelected,
+    NonPositiveDt(f64),
+    DuplicatePos(usize, usize),
+    InvalidBeam {
+        beam: usize,
+        issue: Box<LdsError>,
+    },
+    InvalidNyquistZone,
+    AntOutOfRange {
+        ant: usize,
+        nant: usize,
+    },
+    WrongAntCount {
+        what: &'static str,
+        len: usize,
+        nant: usize,
+    },
+    VisShapeMismatch {
+        vis: Vec<usize>,
+        model: Vec<usize>,
+    },
+    NoObserver,
+    NoMjd,
+    NoLocation,
+    InvalidLatitude(f64),
+    InvalidDigitizer(String),
+    InvalidPulsar(String),
+    InvalidRfi(String),
+    InvalidAntErrors(String),
+    InvalidRequant {
+        stage: &'static str,
+        reason: String,
+    },
+    DispersionTooLong(usize),
+    InvalidRefFreq(f64),
+    InvalidSpectrum {
+        path: PathBuf,
+        reason: String,
+    },
+    NoSkyFreq,
+    ZeroNtap,
+    ZeroUpdateInterval,
+    NumericConversion(&'static str),
+    InvalidCfg(Vec<LdsError>),
+}
+
+impl fmt::Display for LdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LdsError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            LdsError::Yaml { path, source } => {
+                write!(f, "failed to parse {}: {}", path.display(), source)
+            }
+            LdsError::InvalidPfbGeometry { stage, reason } => {
+                write!(f, "invalid {} pfb geometry: {}", stage, reason)
+            }
+            LdsError::EmptyPos => write!(f, "station has no antenna in `pos`"),
+            LdsError::CoarseChOutOfRange { range, nch } => write!(
+                f,
+                "selected_coarse_ch range {:?} exceeds the {} coarse channels",
+                range, nch
+            ),
+            LdsError::ReversedCoarseCh(range) => {
+                write!(
+                    f,
+                    "selected_coarse_ch range {:?} is empty or reversed",
+                    range
+                )
+            }
+            LdsError::OverlappingCoarseCh(a, b) => {
+                write!(f, "selected_coarse_ch ranges {:?} and {:?} overlap", a, b)
+            }
+            LdsError::NoCoarseChSelected => write!(f, "selected_coarse_ch is empty"),
+            LdsError::NonPositiveDt(dt) => write!(f, "dt = {} is not positive and finite", dt),
+            LdsError::DuplicatePos(i, j) => {
+                write!(f, "antennas {} and {} share the same position", i, j)
+            }
+            LdsError::InvalidBeam { beam, issue } => write!(f, "beam {}: {}", beam, issue),
+            LdsError::InvalidNyquistZone => write!(f, "sky_freq.nyquist_zone must start from 1"),
+            LdsError::AntOutOfRange { ant, nant } => {
+                write!(f, "antenna {} is out of range for {} antennas", ant, nant)
+            }
+            LdsError::WrongAntCount { what, len, nant } => {
+                write!(f, "{} {} values for {} antennas", len, what, nant)
+            }
+            LdsError::InvalidSpectrum { path, reason } => {
+                write!(f, "invalid spectrum table {}: {}", path.display(), reason)
+            }
+            LdsError::DispersionTooLong(n) => write!(
+                f,
+                "dispersed pulse exceeds {} samples, raise f_min or lower dm",
+                n
+            ),
+            LdsError::VisShapeMismatch { vis, model } => write!(
+                f,
+                "visibilities of shape {:?} do not match the model of shape {:?}",
+                vis, model
+            ),
+            LdsError::NoObserver => write!(f, "RA/Dec sources need an observer"),
+            LdsError::NoMjd => write!(f, "RA/Dec pointing needs the observation time `mjd`"),
+            LdsError::NoLocation => write!(f, "station cfg has no location"),
+            LdsError::InvalidDigitizer(reason) => write!(f, "invalid digitizer: {}", reason),
+            LdsError::InvalidPulsar(reason) => write!(f, "invalid pulsar: {}", reason),
+            LdsError::InvalidRfi(reason) => write!(f, "invalid rfi: {}", reason),
+            LdsError::InvalidAntErrors(reason) => write!(f, "invalid antenna errors: {}", reason),
+            LdsError::InvalidRequant { stage, reason } => {
+                write!(f, "invalid {} requantization: {}", stage, reason)
+            }
+            LdsError::InvalidLatitude(lat) => {
+                write!(f, "latitude = {} is outside [-90, 90]", lat)
+            }
+            LdsError::InvalidRefFreq(f_ref) => {
+                write!(
+                    f,
+                    "f_ref = {} must be positive and finite for a spectral index",
+                    f_ref
+                )
+            }
+            LdsError::NoSkyFreq => write!(f, "station cfg has no sky_freq mapping"),
+            LdsError::ZeroNtap => write!(f, "spectrum ntap must be positive"),
+            LdsError::ZeroUpdateInterval => {
+                write!(f, "delay update interval must be positive")
+            }
+            LdsError::NumericConversion(what) => {
+                write!(f, "cannot represent {} in the working float type", what)
+            }
+            LdsError::InvalidCfg(issues) => {
+                write!(f, "invalid station cfg:")?;
+                for e in issues {
+                    write!(f, "\n  - {}", e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LdsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LdsError::Io { source, .. } => Some(source),
+            LdsError::Yaml { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
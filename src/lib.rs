@@ -1,5 +1,18 @@
+pub mod calib;
 pub mod cfg;
 pub mod constants;
+pub mod coords;
+pub mod digitizer;
+pub mod error;
+pub mod pulsar;
+pub mod requant;
+pub mod response;
+pub mod rfi;
+pub mod sample;
+pub mod sky_model;
+pub mod spectrum;
 pub mod station;
 pub mod station_src;
+pub mod stream;
 pub mod utils;
+pub mod weights;
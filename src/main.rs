@@ -1,19 +1,1075 @@
-use lds::{cfg::StationCfg, station::Station};
+use progress_bar::*;
+
+use lds::{
+    calib::{model_visibilities, GainSolution, Visibilities},
+    cfg::{load_yaml, StationCfg},
+    constants::earth_rotation_rate,
+    coords::{lst, radec2azze},
+    error::LdsError,
+    pulsar::PulsarSrc,
+    response::ChannelizerModel,
+    rfi::rfi_from_cfg,
+    sky_model::SkyModelSrc,
+    station::Station,
+    station_src::{BroadbandSrc, GeneralSrcBuilder, NoiseSrc, SingleTone, StationSrc, TrackedSrc},
+    stream::Beamforming,
+    utils::{get_freq_to_sample, get_freq_to_sample_in_hz},
+    weights::{BeamWeights, DelayTracker},
+};
+
+use std::{
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use rsdsp::oscillator::COscillator;
 
 use ndarray_npy::write_npy;
 
-use ndarray::ArrayView1;
+use ndarray::{concatenate, parallel::prelude::*, Array1, Array2, Array3, ArrayView1, Axis};
+
+use num::{complex::Complex, traits::FloatConst};
+
+type FloatType = f64;
+type StationType = Station<Complex<FloatType>, FloatType>;
+type SrcType = Box<dyn StationSrc<Complex<FloatType>, FloatType>>;
+/// direction (az, ze) in radians at a given coarse sample
+type DirFn = Box<dyn FnMut(usize) -> (FloatType, FloatType)>;
+
+#[derive(Debug, Parser)]
+#[clap(author, version, about)]
+struct Cli {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Args)]
+struct CommonArgs {
+    #[clap(
+        short('c'),
+        long("cfg"),
+        value_name("station cfg"),
+        default_value("station.yaml"),
+        global(true)
+    )]
+    station_cfg: PathBuf,
+
+    #[clap(
+        short('o'),
+        long("out"),
+        value_name("output dir"),
+        default_value("."),
+        global(true)
+    )]
+    outdir: PathBuf,
+
+    /// Observation time (UTC) as MJD, for RA/Dec directions
+    #[clap(long("mjd"), value_name("mjd"), global(true))]
+    mjd: Option<FloatType>,
+}
+
+#[derive(Debug, Args)]
+struct Pointing {
+    #[clap(
+        short('A'),
+        long("az0"),
+        value_name("az0 in deg"),
+        requires("zenith0"),
+        required_unless_present("ra0")
+    )]
+    azimuth0: Option<FloatType>,
+
+    #[clap(
+        short('Z'),
+        long("ze0"),
+        value_name("ze0 in deg"),
+        requires("azimuth0")
+    )]
+    zenith0: Option<FloatType>,
+
+    #[clap(
+        long("ra0"),
+        value_name("ra0 in deg"),
+        requires("dec0"),
+        conflicts_with("azimuth0")
+    )]
+    ra0: Option<FloatType>,
+
+    #[clap(long("dec0"), value_name("dec0 in deg"), requires("ra0"))]
+    dec0: Option<FloatType>,
+}
+
+#[derive(Debug, Args)]
+struct SrcDir {
+    #[clap(
+        short('a'),
+        long("az"),
+        value_name("az in deg"),
+        requires("zenith"),
+        required_unless_present("ra")
+    )]
+    azimuth: Option<FloatType>,
+
+    #[clap(short('z'), long("ze"), value_name("ze in deg"), requires("azimuth"))]
+    zenith: Option<FloatType>,
+
+    #[clap(
+        long("ra"),
+        value_name("ra in deg"),
+        requires("dec"),
+        conflicts_with("azimuth")
+    )]
+    ra: Option<FloatType>,
+
+    #[clap(long("dec"), value_name("dec in deg"), requires("ra"))]
+    dec: Option<FloatType>,
+}
+
+impl Pointing {
+    fn radec(&self) -> Option<(FloatType, FloatType)> {
+        Some((self.ra0?.to_radians(), self.dec0?.to_radians()))
+    }
+
+    /// (az0, ze0) in radians
+    fn azze(&self, station: &StationType, mjd: Option<FloatType>) -> (FloatType, FloatType) {
+        to_azze(station, mjd, (self.azimuth0, self.zenith0), self.radec())
+    }
+}
+
+impl SrcDir {
+    /// (az, ze) in radians
+    fn azze(&self, station: &StationType, mjd: Option<FloatType>) -> (FloatType, FloatType) {
+        let radec = self
+            .ra
+            .zip(self.dec)
+            .map(|(ra, dec)| (ra.to_radians(), dec.to_radians()));
+        to_azze(station, mjd, (self.azimuth, self.zenith), radec)
+    }
+}
+
+fn to_azze(
+    station: &StationType,
+    mjd: Option<FloatType>,
+    azze: (Option<FloatType>, Option<FloatType>),
+    radec: Option<(FloatType, FloatType)>,
+) -> (FloatType, FloatType) {
+    match (azze, radec) {
+        ((Some(az), Some(ze)), _) => (az.to_radians(), ze.to_radians()),
+        (_, Some((ra, dec))) => {
+            let mjd = mjd.unwrap_or_else(|| {
+                eprintln!("RA/Dec needs the observation time, see --mjd");
+                std::process::exit(1)
+            });
+            exit_on_err(station.radec2azze(ra, dec, mjd))
+        }
+        _ => unreachable!("clap requires one of the frames"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SrcKind {
+    Tone,
+    Delayed,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PowerSrcKind {
+    Noise,
+    Broadband,
+    SkyModel,
+    Rfi,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Dump coarse and fine channel frequencies
+    Freqs,
+
+    /// Dump ideal and two-stage beam gain towards one direction
+    Gain {
+        #[clap(flatten)]
+        pointing: Pointing,
+
+        #[clap(flatten)]
+        src_dir: SrcDir,
+    },
+
+    /// Simulate the coarse and fine channel amplitude response
+    AmplResp {
+        #[clap(flatten)]
+        pointing: Pointing,
+
+        #[clap(flatten)]
+        src_dir: SrcDir,
+
+        #[clap(long("src"), value_enum, default_value("tone"))]
+        src: SrcKind,
+
+        #[clap(
+            short('s'),
+            long("subdiv"),
+            value_name("divid fine channels into"),
+            default_value("2")
+        )]
+        subdiv: usize,
+
+        #[clap(
+            short('l'),
+            long("siglen"),
+            value_name("signal length in pt"),
+            default_value("65536")
+        )]
+        siglen: usize,
+
+        #[clap(short('t'), long("niter"), value_name("niter"), default_value("2"))]
+        niter: usize,
+
+        #[clap(short('d'), long("delay"), value_name("delay file"))]
+        pre_ant_delay: Option<PathBuf>,
+
+        #[clap(long("taper"), value_name("per antenna taper file"))]
+        taper: Option<PathBuf>,
+
+        #[clap(long("flag"), value_name("flagged antennas"), value_delimiter(','))]
+        flagged: Vec<usize>,
+    },
+
+    /// Predict the coarse and fine channel amplitude response from the filter coefficients
+    ModelResp {
+        #[clap(
+            short('s'),
+            long("subdiv"),
+            value_name("divid fine channels into"),
+            default_value("2")
+        )]
+        subdiv: usize,
+    },
+
+    /// Measure beamformed coarse and fine channel power of a random source
+    Power {
+        #[clap(flatten)]
+        pointing: Pointing,
+
+        #[clap(long("src"), value_enum)]
+        src: PowerSrcKind,
+
+        #[clap(short('S'), long("src-cfg"), value_name("source cfg"))]
+        src_cfg: PathBuf,
+
+        #[clap(
+            short('l'),
+            long("siglen"),
+            value_name("signal length in pt"),
+            default_value("65536")
+        )]
+        siglen: usize,
+
+        #[clap(short('t'), long("niter"), value_name("niter"), default_value("8"))]
+        niter: usize,
+    },
+
+    /// Dump coarse and fine channel dynamic spectra of a pulsar
+    Dynspec {
+        #[clap(flatten)]
+        pointing: Pointing,
+
+        #[clap(short('S'), long("src-cfg"), value_name("pulsar cfg"))]
+        src_cfg: PathBuf,
+
+        #[clap(
+            short('l'),
+            long("siglen"),
+            value_name("signal length in pt"),
+            default_value("65536")
+        )]
+        siglen: usize,
+
+        #[clap(short('t'), long("niter"), value_name("niter"), default_value("8"))]
+        niter: usize,
+    },
+
+    /// Track a drifting (az/ze) or sidereal (RA/Dec) noise source, dumping the beam
+    /// power of every block
+    Track {
+        #[clap(flatten)]
+        pointing: Pointing,
+
+        #[clap(long("az-rate"), value_name("deg/s"), default_value("0"))]
+        az_rate: FloatType,
+
+        #[clap(long("ze-rate"), value_name("deg/s"), default_value("0"))]
+        ze_rate: FloatType,
+
+        #[clap(
+            short('u'),
+            long("update-every"),
+            value_name("delay update interval in coarse samples"),
+            default_value("1")
+        )]
+        update_every: usize,
+
+        #[clap(
+            short('l'),
+            long("siglen"),
+            value_name("signal length in pt"),
+            default_value("65536")
+        )]
+        siglen: usize,
+
+        #[clap(short('t'), long("niter"), value_name("niter"), default_value("8"))]
+        niter: usize,
+    },
+
+    /// Solve the antenna gains against a sky model, dump them with the errors left after
+    /// correction and compare the beam towards the pointing before and after correction
+    Calibrate {
+        #[clap(flatten)]
+        pointing: Pointing,
+
+        #[clap(short('S'), long("src-cfg"), value_name("sky model cfg"))]
+        src_cfg: PathBuf,
+
+        #[clap(
+            short('l'),
+            long("siglen"),
+            value_name("signal length in pt"),
+            default_value("65536")
+        )]
+        siglen: usize,
+
+        #[clap(short('t'), long("niter"), value_name("niter"), default_value("8"))]
+        niter: usize,
 
-use serde_yaml::from_reader;
+        #[clap(
+            long("max-iter"),
+            value_name("max solver iterations"),
+            default_value("100")
+        )]
+        max_iter: usize,
+
+        #[clap(long("tol"), value_name("solver tolerance"), default_value("1e-10"))]
+        tol: FloatType,
+    },
+
+    /// Dump the two-stage beam power over an az-ze grid
+    BeamMap {
+        #[clap(flatten)]
+        pointing: Pointing,
+
+        #[clap(long("naz"), value_name("number of az"), default_value("360"))]
+        naz: usize,
+
+        #[clap(long("nze"), value_name("number of ze"), default_value("90"))]
+        nze: usize,
+
+        #[clap(long("max-ze"), value_name("max ze in deg"), default_value("90"))]
+        max_zenith: FloatType,
+    },
+}
+
+fn exit_on_err<V>(result: Result<V, LdsError>) -> V {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    })
+}
+
+fn load_station(path: &Path) -> (StationCfg, StationType) {
+    let station_cfg = exit_on_err(StationCfg::load(path));
+    exit_on_err(station_cfg.validate());
+    let station = exit_on_err(StationType::try_from_cfg(&station_cfg));
+    (station_cfg, station)
+}
+
+/// One value per antenna, e.g. delays or tapers
+fn load_per_ant(path: &Path, what: &'static str, nant: usize) -> Result<Vec<FloatType>, LdsError> {
+    let values: Vec<FloatType> = load_yaml(path)?;
+    if values.len() != nant {
+        return Err(LdsError::WrongAntCount {
+            what,
+            len: values.len(),
+            nant,
+        });
+    }
+    Ok(values)
+}
+
+/// Writes the digitizer statistics, if the station has a digitizer
+fn report_digitizer(station: &StationType, out_dir: &Path) {
+    if station.digitizer.is_some() {
+        let stats = &station.digitizer_stats;
+        let clip_fraction = Array1::from(stats.clip_fraction());
+        let sqnr = Array1::from(stats.sqnr());
+        println!(
+            "clipped fraction: max {:e}, quantization SNR: min {:.2} dB",
+            clip_fraction.fold(0.0, |a: FloatType, &b| a.max(b)),
+            10.0 * sqnr.fold(FloatType::INFINITY, |a, &b| a.min(b)).log10()
+        );
+        write_npy(out_dir.join("clip_fraction.npy"), &clip_fraction).unwrap();
+        write_npy(out_dir.join("sqnr.npy"), &sqnr).unwrap();
+    }
+}
 
 fn main() {
-    let station_cfg: StationCfg =
-        from_reader(std::fs::File::open("station.yaml").unwrap()).unwrap();
-    println!("{:?}", station_cfg);
-    let station = Station::<f64, f64>::from_cfg(&station_cfg);
-    //println!("{:?}", station.fine_ch_freq_in_fs());
-    let a = station.coarse_freq_of_fine_ch_in_fs();
-    let b = station.fine_ch_freq_in_fs();
-    write_npy("a.npy", &ArrayView1::from(&a)).unwrap();
-    write_npy("b.npy", &ArrayView1::from(&b)).unwrap();
+    let cli = Cli::parse();
+
+    let (station_cfg, station) = load_station(&cli.common.station_cfg);
+    let out_dir = cli.common.outdir;
+    let mjd = cli.common.mjd;
+    create_dir_all(&out_dir).unwrap();
+
+    match cli.command {
+        Command::Freqs => freqs(&station, &out_dir),
+        Command::Gain { pointing, src_dir } => gain(
+            &station,
+            pointing.azze(&station, mjd),
+            src_dir.azze(&station, mjd),
+            &out_dir,
+        ),
+        Command::AmplResp {
+            pointing,
+            src_dir,
+            src,
+            subdiv,
+            siglen,
+            niter,
+            pre_ant_delay,
+            taper,
+            flagged,
+        } => {
+            let nant = station.ants.len();
+            let delays = match pre_ant_delay {
+                Some(value) => exit_on_err(load_per_ant(&value, "delay", nant)),
+                None => vec![0.0; nant],
+            };
+            let taper = match taper {
+                Some(value) => exit_on_err(load_per_ant(&value, "taper", nant)),
+                None => vec![1.0; nant],
+            };
+            ampl_resp(
+                &station_cfg,
+                &station,
+                pointing.azze(&station, mjd),
+                src_dir.azze(&station, mjd),
+                src,
+                subdiv,
+                siglen,
+                niter,
+                &delays,
+                &taper,
+                &flagged,
+                &out_dir,
+            )
+        }
+        Command::Power {
+            pointing,
+            src,
+            src_cfg,
+            siglen,
+            niter,
+        } => {
+            let mut station = station;
+            let mut src: SrcType = match src {
+                PowerSrcKind::Noise => Box::new(exit_on_err(NoiseSrc::from_cfg(
+                    &station,
+                    &exit_on_err(load_yaml(&src_cfg)),
+                    &station_cfg.delayer,
+                    siglen,
+                ))),
+                PowerSrcKind::Broadband => Box::new(exit_on_err(BroadbandSrc::from_cfg(
+                    &station,
+                    &exit_on_err(load_yaml(&src_cfg)),
+                    &station_cfg.delayer,
+                    siglen,
+                ))),
+                PowerSrcKind::SkyModel => Box::new(exit_on_err(SkyModelSrc::from_cfg(
+                    &station,
+                    &exit_on_err(load_yaml(&src_cfg)),
+                    &station_cfg.delayer,
+                    siglen,
+                ))),
+                PowerSrcKind::Rfi => exit_on_err(rfi_from_cfg(
+                    &station,
+                    &exit_on_err(load_yaml(&src_cfg)),
+                    &station_cfg.delayer,
+                    siglen,
+                )),
+            };
+            let pointing = pointing.azze(&station, mjd);
+            power(&mut station, src.as_mut(), pointing, niter, &out_dir);
+            report_digitizer(&station, &out_dir)
+        }
+        Command::Dynspec {
+            pointing,
+            src_cfg,
+            siglen,
+            niter,
+        } => {
+            let mut station = station;
+            let pulsar_cfg = exit_on_err(load_yaml(&src_cfg));
+            let mut src = exit_on_err(PulsarSrc::from_cfg(
+                &station,
+                &pulsar_cfg,
+                &station_cfg.delayer,
+                siglen,
+            ));
+            let pointing = pointing.azze(&station, mjd);
+            dynspec(&mut station, &mut src, pointing, niter, &out_dir);
+            report_digitizer(&station, &out_dir)
+        }
+        Command::Track {
+            pointing,
+            az_rate,
+            ze_rate,
+            update_every,
+            siglen,
+            niter,
+        } => {
+            let mut station = station;
+            let (az0, ze0) = pointing.azze(&station, mjd);
+            let blk_time = siglen as FloatType * station.dt;
+            let coarse_time = station.coarse_sample_interval() as FloatType * station.dt;
+            let src = Box::new(NoiseSrc::new(
+                &station,
+                az0,
+                ze0,
+                0.0,
+                1.0,
+                siglen,
+                0,
+                &station_cfg.delayer,
+            ));
+            let (mut src, dir): (_, DirFn) = match pointing.radec() {
+                Some((ra, dec)) => {
+                    // follow the sidereal motion, the rates are ignored
+                    let location = exit_on_err(station.location.ok_or(LdsError::NoLocation));
+                    let latitude = location.latitude.to_radians();
+                    let lst0 = lst(mjd.unwrap(), location.longitude.to_radians());
+                    (
+                        TrackedSrc::sidereal(src, ra, dec, latitude, lst0, blk_time),
+                        Box::new(move |t| {
+                            let lst = lst0
+                                + earth_rotation_rate::<FloatType>() * coarse_time * t as FloatType;
+                            radec2azze(ra, dec, lst, latitude)
+                        }),
+                    )
+                }
+                None => (
+                    TrackedSrc::drift(
+                        src,
+                        az0,
+                        ze0,
+                        az_rate.to_radians() * blk_time,
+                        ze_rate.to_radians() * blk_time,
+                    ),
+                    Box::new(move |t| {
+                        let t = t as FloatType * coarse_time;
+                        (
+                            az0 + az_rate.to_radians() * t,
+                            ze0 + ze_rate.to_radians() * t,
+                        )
+                    }),
+                ),
+            };
+            let tracker = exit_on_err(station.delay_tracker(update_every, dir));
+            track(&mut station, &mut src, tracker, niter, &out_dir);
+            report_digitizer(&station, &out_dir)
+        }
+        Command::Calibrate {
+            pointing,
+            src_cfg,
+            siglen,
+            niter,
+            max_iter,
+            tol,
+        } => {
+            let mut station = station;
+            let sky_cfg = exit_on_err(load_yaml(&src_cfg));
+            let mut src = exit_on_err(SkyModelSrc::from_cfg(
+                &station,
+                &sky_cfg,
+                &station_cfg.delayer,
+                siglen,
+            ));
+            let model = exit_on_err(model_visibilities(&station, &src.directions));
+            let pointing = pointing.azze(&station, mjd);
+            calibrate(
+                &mut station,
+                &mut src,
+                &model,
+                pointing,
+                niter,
+                max_iter,
+                tol,
+                &out_dir,
+            );
+            report_digitizer(&station, &out_dir)
+        }
+        Command::ModelResp { subdiv } => model_resp(&station, subdiv, &out_dir),
+        Command::BeamMap {
+            pointing,
+            naz,
+            nze,
+            max_zenith,
+        } => beam_map(
+            &station,
+            pointing.azze(&station, mjd),
+            naz,
+            nze,
+            max_zenith,
+            &out_dir,
+        ),
+    }
+}
+
+fn freqs(station: &StationType, out_dir: &Path) {
+    let all_coarse_ch: Vec<_> = (0..station.ncoarse_ch()).collect();
+    let coarse = station.coarse_ch_freq_in_fs(&all_coarse_ch);
+    let coarse_of_fine = station.coarse_freq_of_fine_ch_in_fs();
+    let fine = station.fine_ch_freq_in_fs();
+    write_npy(out_dir.join("coarse_freq.npy"), &ArrayView1::from(&coarse)).unwrap();
+    write_npy(
+        out_dir.join("coarse_of_fine_freq.npy"),
+        &ArrayView1::from(&coarse_of_fine),
+    )
+    .unwrap();
+    write_npy(out_dir.join("fine_freq.npy"), &ArrayView1::from(&fine)).unwrap();
+
+    for b in 0..station.beam_pointings.len() {
+        write_npy(
+            out_dir.join(format!("beam{}_fine_freq.npy", b)),
+            &Array1::from(station.beam_fine_ch_freq_in_fs(b)),
+        )
+        .unwrap();
+    }
+
+    if station.sky_freq.is_some() {
+        let coarse = exit_on_err(station.freq_in_hz(&coarse));
+        let coarse_of_fine = exit_on_err(station.freq_in_hz(&coarse_of_fine));
+        let fine = exit_on_err(station.freq_in_hz(&fine));
+        write_npy(
+            out_dir.join("coarse_freq_hz.npy"),
+            &ArrayView1::from(&coarse),
+        )
+        .unwrap();
+        write_npy(
+            out_dir.join("coarse_of_fine_freq_hz.npy"),
+            &ArrayView1::from(&coarse_of_fine),
+        )
+        .unwrap();
+        write_npy(out_dir.join("fine_freq_hz.npy"), &ArrayView1::from(&fine)).unwrap();
+    }
+}
+
+fn gain(
+    station: &StationType,
+    (az0, ze0): (FloatType, FloatType),
+    (az, ze): (FloatType, FloatType),
+    out_dir: &Path,
+) {
+    let gain_ideal = Array1::from(station.gain_ideal(az, ze, az0, ze0));
+    let gain_2stage = Array1::from(station.gain_2stage(az, ze, az0, ze0));
+
+    write_npy(out_dir.join("gain_ideal.npy"), &gain_ideal).unwrap();
+    write_npy(out_dir.join("gain_2stage.npy"), &gain_2stage).unwrap();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ampl_resp(
+    station_cfg: &StationCfg,
+    station: &StationType,
+    (az0, ze0): (FloatType, FloatType),
+    (az, ze): (FloatType, FloatType),
+    src_kind: SrcKind,
+    subdiv: usize,
+    siglen: usize,
+    niter: usize,
+    delays: &[FloatType],
+    taper: &[FloatType],
+    flagged: &[usize],
+    out_dir: &Path,
+) {
+    let freq_to_sample = get_freq_to_sample(station, subdiv);
+
+    let omega_to_sample: Vec<_> = freq_to_sample
+        .iter()
+        .map(|f| 2.0 * f * FloatType::PI())
+        .collect();
+
+    let nfreq = freq_to_sample.len();
+
+    let mut coarse_resp = Array2::<FloatType>::zeros((station_cfg.coarse_pfb.nch, nfreq));
+    let mut fine_resp = Array2::<FloatType>::zeros((station_cfg.total_nfine_ch(), nfreq));
+
+    let digital_delay = station.calc_required_digital_delay(az0, ze0);
+    let weights = exit_on_err(
+        station
+            .delay_weights(&digital_delay)
+            .with_taper(taper)
+            .and_then(|w| w.with_flagged(flagged)),
+    );
+    init_progress_bar(nfreq);
+    set_progress_bar_action("Computing", Color::Blue, Style::Bold);
+
+    coarse_resp
+        .axis_iter_mut(Axis(1))
+        .into_par_iter()
+        .zip(fine_resp.axis_iter_mut(Axis(1)))
+        .zip(omega_to_sample.par_iter())
+        .for_each(|((mut coarse_resp1, mut fine_resp1), &omega)| {
+            let mut station = station.clone();
+
+            let (coarse1, fine1) = match src_kind {
+                SrcKind::Tone => {
+                    let mut src =
+                        SingleTone::new(&station, az, ze, omega, siglen).with_delay(delays);
+                    let mut result = station.acquire_fine_weighted(&mut src, &weights);
+                    for _ in 1..niter {
+                        result = station.acquire_fine_weighted(&mut src, &weights);
+                    }
+                    result
+                }
+                SrcKind::Delayed => {
+                    let mut src_builder = GeneralSrcBuilder::new(
+                        &station,
+                        az,
+                        ze,
+                        station_cfg.delayer.max_delay,
+                        station_cfg.delayer.half_tap,
+                    );
+                    let mut osc = COscillator::new(0.0, omega);
+                    let mut acquire = || {
+                        let signal: Vec<_> = (0..siglen).map(|_| osc.get()).collect();
+                        let mut src = src_builder.build(&signal);
+                        station.acquire_fine_weighted(&mut src, &weights)
+                    };
+                    let mut result = acquire();
+                    for _ in 1..niter {
+                        result = acquire();
+                    }
+                    result
+                }
+            };
+
+            coarse_resp1.assign(
+                &coarse1
+                    .map(|y| y.norm_sqr())
+                    .mean_axis(Axis(1))
+                    .unwrap()
+                    .view(),
+            );
+
+            fine_resp1.assign(
+                &fine1
+                    .map(|y| y.norm_sqr())
+                    .mean_axis(Axis(1))
+                    .unwrap()
+                    .view(),
+            );
+            inc_progress_bar();
+        });
+
+    finalize_progress_bar();
+
+    println!("Done, dumping results");
+    write_npy(out_dir.join("coarse.npy"), &coarse_resp).unwrap();
+    write_npy(out_dir.join("fine.npy"), &fine_resp).unwrap();
+
+    let freq_coarse = Array1::from_vec(
+        station.coarse_ch_freq_in_fs(&(0..station.ncoarse_ch()).collect::<Vec<_>>()),
+    );
+    let freq_fine = Array1::from_vec(station.fine_ch_freq_in_fs());
+
+    write_npy(out_dir.join("coarse_freq.npy"), &freq_coarse).unwrap();
+    write_npy(out_dir.join("fine_freq.npy"), &freq_fine).unwrap();
+    write_npy(out_dir.join("freq.npy"), &ArrayView1::from(&freq_to_sample)).unwrap();
+
+    if station.sky_freq.is_some() {
+        let freq_hz = exit_on_err(get_freq_to_sample_in_hz(station, subdiv));
+        write_npy(out_dir.join("freq_hz.npy"), &ArrayView1::from(&freq_hz)).unwrap();
+    }
+}
+
+fn power(
+    station: &mut StationType,
+    src: &mut dyn StationSrc<Complex<FloatType>, FloatType>,
+    (az0, ze0): (FloatType, FloatType),
+    niter: usize,
+    out_dir: &Path,
+) {
+    let weights = station.delay_weights(&station.calc_required_digital_delay(az0, ze0));
+
+    let mut coarse_power = Array1::<FloatType>::zeros(station.ncoarse_ch());
+    let mut fine_power = Array1::<FloatType>::zeros(station.fine_ch_freq_in_fs().len());
+    for (coarse1, fine1) in station
+        .stream(src, Beamforming::Fixed(weights))
+        .with_nblk(niter)
+    {
+        coarse_power = coarse_power + coarse1.map(|y| y.norm_sqr()).mean_axis(Axis(1)).unwrap();
+        fine_power = fine_power + fine1.map(|y| y.norm_sqr()).mean_axis(Axis(1)).unwrap();
+    }
+    coarse_power /= niter as FloatType;
+    fine_power /= niter as FloatType;
+
+    write_npy(out_dir.join("coarse_power.npy"), &coarse_power).unwrap();
+    write_npy(out_dir.join("fine_power.npy"), &fine_power).unwrap();
+    report_requant(station);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn calibrate(
+    station: &mut StationType,
+    src: &mut dyn StationSrc<Complex<FloatType>, FloatType>,
+    model: &Array3<Complex<FloatType>>,
+    (az0, ze0): (FloatType, FloatType),
+    niter: usize,
+    max_iter: usize,
+    tol: FloatType,
+    out_dir: &Path,
+) {
+    let nant = station.ants.len();
+    let mut vis = Visibilities::new(station.ncoarse_ch(), nant);
+    for _ in 0..niter {
+        let signal = src.get_sig(station);
+        exit_on_err(vis.accumulate(&station.channelize(&signal)));
+    }
+    let solution = exit_on_err(GainSolution::solve(&vis.mean(), model, max_iter, tol));
+    let nconverged = solution.converged.iter().filter(|&&c| c).count();
+    println!(
+        "{} of {} channels converged",
+        nconverged,
+        solution.converged.len()
+    );
+
+    // without antenna errors the ideal gains are all ones
+    let errors = station
+        .ant_errors
+        .clone()
+        .unwrap_or_else(|| BeamWeights::uniform(nant, station.ncoarse_ch()));
+    let residual = solution.residual(&errors);
+    let residual_ampl = residual
+        .map(|x| (x.norm() - 1.0).powi(2))
+        .mean_axis(Axis(0))
+        .unwrap()
+        .mapv(FloatType::sqrt);
+    let residual_phase = residual
+        .map(|x| x.arg().powi(2))
+        .mean_axis(Axis(0))
+        .unwrap()
+        .mapv(FloatType::sqrt);
+
+    write_npy(
+        out_dir.join("gain_ampl.npy"),
+        &solution.gains.map(|x| x.norm()),
+    )
+    .unwrap();
+    write_npy(
+        out_dir.join("gain_phase.npy"),
+        &solution.gains.map(|x| x.arg()),
+    )
+    .unwrap();
+    write_npy(out_dir.join("residual_ampl.npy"), &residual_ampl).unwrap();
+    write_npy(out_dir.join("residual_phase.npy"), &residual_phase).unwrap();
+
+    // beamform the same signal with the plain and the corrected weights, and with the
+    // plain weights on an error-free copy of the station as the reference; the gain
+    // common to all antennas is unobservable, so like the residual both beams are
+    // scaled by the mean antenna gain they leave in each channel
+    let zero = Complex::<FloatType>::new(0.0, 0.0);
+    let unit_mean = |w: BeamWeights<FloatType>, gains: Array2<Complex<FloatType>>| {
+        let mean = gains.mean_axis(Axis(0)).unwrap();
+        let scale = Array2::from_shape_fn(w.w.dim(), |(_, c)| {
+            if mean[c].norm_sqr() > 0.0 {
+                mean[c].inv()
+            } else {
+                zero
+            }
+        });
+        w.with_gains(scale.view())
+    };
+    let weights = station.delay_weights(&station.calc_required_digital_delay(az0, ze0));
+    let plain = unit_mean(weights.clone(), errors.w.clone());
+    let corrected = unit_mean(
+        solution.weights(weights.clone()),
+        &errors.w * &solution.corrections(),
+    );
+    let mut ideal = station.clone().with_ant_errors(None);
+    let mut cross = Array2::from_elem((2, station.ncoarse_ch()), zero);
+    let mut ideal_power = Array1::<FloatType>::zeros(station.ncoarse_ch());
+    for _ in 0..niter {
+        let signal = src.get_sig(station);
+        let channelized = station.channelize(&signal);
+        let reference = beam_of(&weights, &ideal.channelize(&signal));
+        for (mut c, w) in cross.outer_iter_mut().zip([&plain, &corrected]) {
+            let beam = beam_of(w, &channelized);
+            c += &(&beam * &reference.mapv(|x| x.conj())).sum_axis(Axis(1));
+        }
+        ideal_power += &reference.mapv(|x| x.norm_sqr()).sum_axis(Axis(1));
+    }
+    // complex gain of the beam relative to the error-free one
+    let beam_gain = cross / &ideal_power;
+    let beam_ampl_err = beam_gain.mapv(|x| x.norm() - 1.0);
+    let beam_phase_err = beam_gain.mapv(|x| x.arg());
+    let rms = |x: ArrayView1<FloatType>| x.mapv(|y| y * y).mean().unwrap().sqrt();
+    for (i, stage) in ["before", "after"].iter().enumerate() {
+        println!(
+            "beam error {} correction: amplitude {:e} rms, phase {:e} rad rms",
+            stage,
+            rms(beam_ampl_err.row(i)),
+            rms(beam_phase_err.row(i))
+        );
+    }
+    write_npy(out_dir.join("beam_ampl_err.npy"), &beam_ampl_err).unwrap();
+    write_npy(out_dir.join("beam_phase_err.npy"), &beam_phase_err).unwrap();
+    report_requant(station);
+}
+
+/// Weighted sum over the antennas of `channelized`, shape (ncoarse_ch, ntime)
+fn beam_of(
+    weights: &BeamWeights<FloatType>,
+    channelized: &[Array2<Complex<FloatType>>],
+) -> Array2<Complex<FloatType>> {
+    let mut beam = Array2::from_elem(channelized[0].dim(), Complex::<FloatType>::new(0.0, 0.0));
+    for (a, x) in channelized.iter().enumerate() {
+        let mut x = x.clone();
+        weights.apply(a, &mut x);
+        beam += &x;
+    }
+    beam
+}
+
+fn track(
+    station: &mut StationType,
+    src: &mut dyn StationSrc<Complex<FloatType>, FloatType>,
+    tracker: DelayTracker<FloatType>,
+    niter: usize,
+    out_dir: &Path,
+) {
+    let mut coarse_power = Array2::<FloatType>::zeros((niter, station.ncoarse_ch()));
+    let mut fine_power = Array2::<FloatType>::zeros((niter, station.fine_ch_freq_in_fs().len()));
+    for (i, (coarse1, fine1)) in station
+        .stream(src, Beamforming::Tracking(tracker))
+        .with_nblk(niter)
+        .enumerate()
+    {
+        coarse_power
+            .row_mut(i)
+            .assign(&coarse1.map(|y| y.norm_sqr()).mean_axis(Axis(1)).unwrap());
+        fine_power
+            .row_mut(i)
+            .assign(&fine1.map(|y| y.norm_sqr()).mean_axis(Axis(1)).unwrap());
+    }
+
+    write_npy(out_dir.join("track_coarse_power.npy"), &coarse_power).unwrap();
+    write_npy(out_dir.join("track_fine_power.npy"), &fine_power).unwrap();
+    report_requant(station);
+}
+
+fn dynspec(
+    station: &mut StationType,
+    src: &mut dyn StationSrc<Complex<FloatType>, FloatType>,
+    (az0, ze0): (FloatType, FloatType),
+    niter: usize,
+    out_dir: &Path,
+) {
+    let weights = station.delay_weights(&station.calc_required_digital_delay(az0, ze0));
+
+    let (coarse, fine): (Vec<_>, Vec<_>) = (0..niter)
+        .map(|_| {
+            let (coarse1, fine1) = station.acquire_fine_weighted(src, &weights);
+            (coarse1.map(|y| y.norm_sqr()), fine1.map(|y| y.norm_sqr()))
+        })
+        .unzip();
+    let coarse = concatenate(
+        Axis(1),
+        &coarse.iter().map(|x| x.view()).collect::<Vec<_>>(),
+    )
+    .unwrap();
+    let fine = concatenate(Axis(1), &fine.iter().map(|x| x.view()).collect::<Vec<_>>()).unwrap();
+
+    write_npy(out_dir.join("coarse_dynspec.npy"), &coarse).unwrap();
+    write_npy(out_dir.join("fine_dynspec.npy"), &fine).unwrap();
+    report_requant(station);
+}
+
+fn report_requant(station: &StationType) {
+    for (stage, requant) in [
+        ("coarse", &station.requant.coarse),
+        ("beam", &station.requant.beam),
+        ("fine", &station.requant.fine),
+    ] {
+        if let Some(requant) = requant {
+            println!(
+                "{} requantization clipped fraction: {:e}",
+                stage,
+                requant.clip_fraction()
+            );
+        }
+    }
+    for (i, requant) in station.beam_requant.iter().enumerate() {
+        if let Some(requant) = requant {
+            println!(
+                "beam {} fine requantization clipped fraction: {:e}",
+                i,
+                requant.clip_fraction()
+            );
+        }
+    }
+}
+
+fn model_resp(station: &StationType, subdiv: usize, out_dir: &Path) {
+    let freq_to_sample = get_freq_to_sample(station, subdiv);
+    let model = ChannelizerModel::from_station(station);
+
+    write_npy(
+        out_dir.join("coarse.npy"),
+        &model.coarse_resp(&freq_to_sample),
+    )
+    .unwrap();
+    write_npy(out_dir.join("fine.npy"), &model.fine_resp(&freq_to_sample)).unwrap();
+    write_npy(
+        out_dir.join("coarse_freq.npy"),
+        &ArrayView1::from(&model.coarse_ch_freq),
+    )
+    .unwrap();
+    write_npy(
+        out_dir.join("fine_freq.npy"),
+        &ArrayView1::from(&model.fine_ch_freq),
+    )
+    .unwrap();
+    write_npy(out_dir.join("freq.npy"), &ArrayView1::from(&freq_to_sample)).unwrap();
+}
+
+fn beam_map(
+    station: &StationType,
+    (az0, ze0): (FloatType, FloatType),
+    naz: usize,
+    nze: usize,
+    max_zenith: FloatType,
+    out_dir: &Path,
+) {
+    let az_list = Array1::from_iter((0..naz).map(|i| 360.0 * i as FloatType / naz as FloatType));
+    let ze_list = Array1::<FloatType>::linspace(0.0, max_zenith, nze);
+
+    let nfine = station.fine_ch_freq_in_fs().len();
+    let mut beam = Array3::<FloatType>::zeros((nze, naz, nfine));
+
+    beam.axis_iter_mut(Axis(0))
+        .into_par_iter()
+        .zip(ze_list.as_slice().unwrap().par_iter())
+        .for_each(|(mut beam1, &ze)| {
+            for (mut beam2, &az) in beam1.axis_iter_mut(Axis(0)).zip(az_list.iter()) {
+                let g = station.gain_2stage(az.to_radians(), ze.to_radians(), az0, ze0);
+                beam2.assign(&Array1::from_iter(g.iter().map(|x| x.norm_sqr())));
+            }
+        });
+
+    write_npy(out_dir.join("beam_map.npy"), &beam).unwrap();
+    write_npy(out_dir.join("az.npy"), &az_list).unwrap();
+    write_npy(out_dir.join("ze.npy"), &ze_list).unwrap();
 }
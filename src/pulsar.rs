@@ -0,0 +1,271 @@
+use num::{
+    complex::Complex,
+    traits::{Float, FloatConst},
+};
+
+use rsdsp::{cfg::DelayerCfg, utils::fftfreq};
+
+use rustfft::{FftNum, FftPlanner};
+
+use crate::{
+    cfg::PulsarCfg,
+    constants::dispersion_const,
+    error::LdsError,
+    sample::Sample,
+    station::Station,
+    station_src::{GeneralSrcBuilder, StationSrc, SteerableSrc},
+};
+
+const MAX_TEMPLATE_LEN: usize = 1 << 26;
+
+pub struct PulsarSrc<R, T>
+where
+    R: std::fmt::Debug,
+    T: std::fmt::Debug + Float,
+{
+    /// one dispersed pulse starting at its first sample
+    pub template: Vec<Complex<T>>,
+    /// in samples
+    pub t0: f64,
+    pub period: Option<f64>,
+    pub sig_len: usize,
+    /// absolute index of the next sample to emit
+    pub t: usize,
+    pub sky: GeneralSrcBuilder<R, T>,
+}
+
+impl<R, T> PulsarSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + FftNum,
+    Complex<T>: ndarray::ScalarOperand,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+{
+    pub fn from_cfg(
+        station: &Station<R, T>,
+        cfg: &PulsarCfg,
+        delayer_cfg: &DelayerCfg,
+        sig_len: usize,
+    ) -> Result<Self, LdsError> {
+        if !(cfg.width > 0.0 && cfg.width.is_finite()) {
+            return Err(LdsError::InvalidPulsar(format!(
+                "width = {} is not positive",
+                cfg.width
+            )));
+        }
+        if let Some(period) = cfg.period.filter(|p| !(*p > 0.0 && p.is_finite())) {
+            return Err(LdsError::InvalidPulsar(format!(
+                "period = {} is not positive",
+                period
+            )));
+        }
+        let (az, ze) = cfg.pos.azze(station.location.as_ref(), cfg.mjd)?;
+        let (az, ze) = (T::from(az).unwrap(), T::from(ze).unwrap());
+        let dt = station.dt.to_f64().unwrap();
+        let sigma = cfg.width / (8.0 * 2.0.ln()).sqrt() / dt;
+        let template = if cfg.dm == 0.0 {
+            Self::template(cfg, sigma, |_| (0.0, 1.0), 1)
+        } else {
+            let sky_freq = station.sky_freq.as_ref().ok_or(LdsError::NoSkyFreq)?;
+            let sign = if sky_freq.inverted() { -1.0 } else { 1.0 };
+            let dd = dispersion_const::<f64>() * cfg.dm;
+
+            // a real signal needs a Hermitian spectrum, see `template`
+            let fold = |f: f64| if R::IS_REAL { f.abs() } else { f };
+            let mut n = (16.0 * sigma).ceil().max(16.0) as usize;
+            n = n.next_power_of_two();
+            loop {
+                let freqs: Vec<_> = fftfreq::<f64>(n)
+                    .into_iter()
+                    .map(|f| sky_freq.freq_in_hz(fold(f), dt))
+                    .filter(|&f| f > 0.0 && f >= cfg.f_min)
+                    .collect();
+                let f_max = freqs.iter().cloned().fold(0.0, f64::max);
+                let f_low = freqs.iter().cloned().fold(f64::INFINITY, f64::min);
+                let sweep = dd * (1.0 / (f_low * f_low) - 1.0 / (f_max * f_max)) / dt;
+                if sweep + 8.0 * sigma <= n as f64 {
+                    // phase with group delay dd*(1/f^2-1/f_max^2), see freq_in_hz for sign
+                    let phase = |f: f64| {
+                        let f_hz = sky_freq.freq_in_hz(fold(f), dt);
+                        if f_hz > 0.0 && f_hz >= cfg.f_min {
+                            let phi = 2.0
+                                * std::f64::consts::PI
+                                * dd
+                                * (1.0 / f_hz + f_hz / (f_max * f_max));
+                            let sign = if fold(f) == f { sign } else { -sign };
+                            (sign * phi, 1.0)
+                        } else {
+                            (0.0, 0.0)
+                        }
+                    };
+                    break Self::template(cfg, sigma, phase, n);
+                }
+                n *= 2;
+                if n > MAX_TEMPLATE_LEN {
+                    return Err(LdsError::DispersionTooLong(MAX_TEMPLATE_LEN));
+                }
+            }
+        };
+
+        Ok(PulsarSrc {
+            template,
+            t0: cfg.t0 / dt,
+            period: cfg.period.map(|p| p / dt),
+            sig_len,
+            t: 0,
+            sky: GeneralSrcBuilder::new(
+                station,
+                az,
+                ze,
+                delayer_cfg.max_delay,
+                delayer_cfg.half_tap,
+            ),
+        })
+    }
+
+    /// `phase` gives the dispersion phase and a mask for a normalized frequency,
+    /// the pulse peaks at 4 sigma after the template start at the top of the band;
+    /// for real samples `phase` must be odd in the frequency and the unpaired DC and
+    /// Nyquist bins keep their real part only, so that the template is real
+    fn template<F>(cfg: &PulsarCfg, sigma: f64, phase: F, n: usize) -> Vec<Complex<T>>
+    where
+        F: Fn(f64) -> (f64, f64),
+    {
+        let n = n.max((16.0 * sigma).ceil() as usize).max(1);
+        let n0 = 4.0 * sigma;
+        let two_pi = 2.0 * std::f64::consts::PI;
+        let norm = cfg.ampl * sigma * two_pi.sqrt() / n as f64;
+        let mut spec: Vec<_> = fftfreq::<f64>(n)
+            .into_iter()
+            .map(|f| {
+                let (phi, mask) = phase(f);
+                let g = (-0.5 * (two_pi * sigma * f).powi(2)).exp() * mask * norm;
+                let mut c = Complex::<f64>::from_polar(g, phi - two_pi * f * n0);
+                if R::IS_REAL && (f == 0.0 || f == -0.5) {
+                    c.im = 0.0;
+                }
+                Complex::<T>::new(T::from(c.re).unwrap(), T::from(c.im).unwrap())
+            })
+            .collect();
+        FftPlanner::<T>::new()
+            .plan_fft_inverse(n)
+            .process(&mut spec);
+        spec
+    }
+}
+
+impl<R, T> StationSrc<R, T> for PulsarSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    Complex<T>: ndarray::ScalarOperand,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+{
+    fn get_sig(&mut self, _: &Station<R, T>) -> Vec<Vec<R>> {
+        let mut signal = vec![Complex::<T>::new(T::zero(), T::zero()); self.sig_len];
+        let t1 = self.t as f64;
+        let t2 = (self.t + self.sig_len) as f64;
+        let len = self.template.len() as f64;
+
+        let starts: Vec<f64> = match self.period {
+            None => vec![self.t0.round()],
+            Some(p) => {
+                let k1 = ((t1 - len - self.t0) / p).ceil().max(0.0) as usize;
+                let k2 = ((t2 - self.t0) / p).floor();
+                if k2 < 0.0 {
+                    vec![]
+                } else {
+                    (k1..=k2 as usize)
+                        .map(|k| (self.t0 + k as f64 * p).round())
+                        .collect()
+                }
+            }
+        };
+
+        for s in starts {
+            if s + len <= t1 || s >= t2 {
+                continue;
+            }
+            for (i, x) in signal.iter_mut().enumerate() {
+                let j = t1 + i as f64 - s;
+                if j >= 0.0 && j < len {
+                    *x += self.template[j as usize];
+                }
+            }
+        }
+
+        self.t += self.sig_len;
+        let signal: Vec<_> = signal.into_iter().map(R::from_complex).collect();
+        self.sky.build(&signal).signal
+    }
+}
+
+impl<R, T> SteerableSrc<R, T> for PulsarSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    Complex<T>: ndarray::ScalarOperand,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+{
+    fn set_dir(&mut self, station: &Station<R, T>, az: T, ze: T) {
+        self.sky.set_dir(station, az, ze);
+    }
+}
@@ -0,0 +1,135 @@
+use ndarray::ArrayViewMut2;
+
+use num::{complex::Complex, traits::Float};
+
+use crate::cfg::{RequantCfg, RequantStagesCfg, Rounding};
+
+impl Rounding {
+    pub fn round<T: Float>(self, x: T) -> T {
+        match self {
+            Rounding::Nearest => x.round(),
+            Rounding::Floor => x.floor(),
+            Rounding::Zero => x.trunc(),
+            Rounding::Even => {
+                let f = x.floor();
+                let d = x - f;
+                let half = T::from(0.5).unwrap();
+                let two = T::one() + T::one();
+                if d > half || (d == half && f % two != T::zero()) {
+                    f + T::one()
+                } else {
+                    f
+                }
+            }
+        }
+    }
+}
+
+/// Requantizes channelized data to `nbits` per real component after scaling each
+/// channel by its gain; the output is scaled back to the input units
+#[derive(Debug, Clone)]
+pub struct Requantizer<T> {
+    pub lsb: T,
+    /// saturation in units of `lsb`
+    pub max_level: T,
+    /// per channel, unity if empty
+    pub gain: Vec<T>,
+    pub rounding: Rounding,
+    pub nsamples: usize,
+    pub nclipped: usize,
+}
+
+impl<T> Requantizer<T>
+where
+    T: Float,
+{
+    pub fn new(nbits: u32, lsb: T, gain: Vec<T>, rounding: Rounding) -> Self {
+        Requantizer {
+            lsb,
+            max_level: T::from((1_u64 << (nbits - 1)) - 1).unwrap(),
+            gain,
+            rounding,
+            nsamples: 0,
+            nclipped: 0,
+        }
+    }
+
+    pub fn from_cfg(cfg: &RequantCfg) -> Self {
+        Self::new(
+            cfg.nbits,
+            T::from(cfg.lsb).unwrap(),
+            cfg.gain.iter().map(|&g| T::from(g).unwrap()).collect(),
+            cfg.rounding,
+        )
+    }
+
+    /// Same settings with the clip statistics cleared
+    pub fn with_zero_stats(self) -> Self {
+        Requantizer {
+            nsamples: 0,
+            nclipped: 0,
+            ..self
+        }
+    }
+
+    pub fn clip_fraction(&self) -> f64 {
+        self.nclipped as f64 / self.nsamples.max(1) as f64
+    }
+
+    /// `x` has one row per channel
+    pub fn apply(&mut self, mut x: ArrayViewMut2<Complex<T>>) {
+        assert!(self.gain.is_empty() || self.gain.len() == x.nrows());
+        let mut nclipped = 0;
+        for (c, mut row) in x.rows_mut().into_iter().enumerate() {
+            let g = self.gain.get(c).cloned().unwrap_or_else(T::one);
+            let step = self.lsb / g;
+            let mut quantize = |y: T| {
+                let level = self.rounding.round(y / step);
+                if level.abs() > self.max_level {
+                    nclipped += 1;
+                    self.max_level.copysign(level) * step
+                } else {
+                    level * step
+                }
+            };
+            for y in row.iter_mut() {
+                let re = quantize(y.re);
+                *y = Complex::<T>::new(re, quantize(y.im));
+            }
+        }
+        self.nsamples += x.len() * 2;
+        self.nclipped += nclipped;
+    }
+}
+
+/// Optional requantization after the coarse PFB of every antenna, after the beam
+/// sum and after the fine PFB
+#[derive(Debug, Clone)]
+pub struct RequantStages<T> {
+    pub coarse: Option<Requantizer<T>>,
+    pub beam: Option<Requantizer<T>>,
+    pub fine: Option<Requantizer<T>>,
+}
+
+impl<T> Default for RequantStages<T> {
+    fn default() -> Self {
+        RequantStages {
+            coarse: None,
+            beam: None,
+            fine: None,
+        }
+    }
+}
+
+impl<T> RequantStages<T>
+where
+    T: Float,
+{
+    pub fn from_cfg(cfg: &RequantStagesCfg) -> Self {
+        RequantStages {
+            coarse: cfg.coarse.as_ref().map(Requantizer::from_cfg),
+            beam: cfg.beam.as_ref().map(Requantizer::from_cfg),
+            fine: cfg.fine.as_ref().map(Requantizer::from_cfg),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use ndarray::{Array2, ScalarOperand, Zip};
+
+use num::{
+    complex::Complex,
+    traits::{Float, FloatConst, NumAssign},
+};
+
+use std::{iter::Sum, ops::Mul};
+
+use rustfft::FftNum;
+
+use crate::{sample::Sample, station::Station};
+
+/// Channel response predicted from the prototype filters of a `Station`,
+/// normalized to unit power gain at the centre of each channel.
+#[derive(Debug, Clone)]
+pub struct ChannelizerModel<T> {
+    pub ncoarse_ch: usize,
+    pub coeff_coarse: Vec<T>,
+    pub coeff_fine: Vec<T>,
+    pub coarse_ch_freq: Vec<T>,
+    pub fine_ch_freq: Vec<T>,
+    pub coarse_freq_of_fine_ch: Vec<T>,
+}
+
+pub fn wrap_freq<T>(f: T) -> T
+where
+    T: Float,
+{
+    let half = T::from(0.5).unwrap();
+    f - (f + half).floor()
+}
+
+pub fn filter_resp<T>(coeff: &[T], f: T) -> Complex<T>
+where
+    T: Float + FloatConst + Sum,
+{
+    let two = T::one() + T::one();
+    coeff
+        .iter()
+        .enumerate()
+        .map(|(n, &c)| {
+            Complex::<T>::new(T::zero(), -two * T::PI() * f * T::from(n).unwrap()).exp() * c
+        })
+        .sum::<Complex<T>>()
+}
+
+fn normalized<T>(coeff: &[T]) -> Vec<T>
+where
+    T: Float + Sum,
+{
+    let norm = coeff.iter().cloned().sum::<T>();
+    coeff.iter().map(|&c| c / norm).collect()
+}
+
+impl<T> ChannelizerModel<T>
+where
+    T: Float + FloatConst + Sum + Send + Sync + std::fmt::Debug,
+{
+    pub fn from_station<R>(station: &Station<R, T>) -> Self
+    where
+        T: ScalarOperand + NumAssign + FftNum + Default,
+        Complex<T>: Copy + std::convert::From<R> + Sum + Default + ScalarOperand,
+        R: Copy
+            + Mul<T, Output = R>
+            + Default
+            + ScalarOperand
+            + NumAssign
+            + std::fmt::Debug
+            + Sum
+            + Sync
+            + Send
+            + Sample<T>,
+    {
+        let ncoarse_ch = station.ncoarse_ch();
+        ChannelizerModel {
+            ncoarse_ch,
+            coeff_coarse: normalized(&station.coeff_stage1),
+            coeff_fine: normalized(&station.coeff_stage2),
+            coarse_ch_freq: station.coarse_ch_freq_in_fs(&(0..ncoarse_ch).collect::<Vec<_>>()),
+            fine_ch_freq: station.fine_ch_freq_in_fs(),
+            coarse_freq_of_fine_ch: station.coarse_freq_of_fine_ch_in_fs(),
+        }
+    }
+
+    /// power response with shape (ncoarse_ch, freqs.len())
+    pub fn coarse_resp(&self, freqs: &[T]) -> Array2<T> {
+        let mut result = Array2::zeros((self.coarse_ch_freq.len(), freqs.len()));
+        Zip::indexed(&mut result).par_for_each(|(c, i), r| {
+            *r = filter_resp(
+                &self.coeff_coarse,
+                wrap_freq(freqs[i] - self.coarse_ch_freq[c]),
+            )
+            .norm_sqr()
+        });
+        result
+    }
+
+    /// power response with shape (total_nfine_ch, freqs.len())
+    pub fn fine_resp(&self, freqs: &[T]) -> Array2<T> {
+        // the oversampled coarse channels are sampled at 2/ncoarse_ch of fs
+        let coarse_rate = T::from(2).unwrap() / T::from(self.ncoarse_ch).unwrap();
+        let mut result = Array2::zeros((self.fine_ch_freq.len(), freqs.len()));
+        Zip::indexed(&mut result).par_for_each(|(c, i), r| {
+            let fc = self.coarse_freq_of_fine_ch[c];
+            let df = wrap_freq(freqs[i] - fc);
+            let coarse = filter_resp(&self.coeff_coarse, df).norm_sqr();
+            let fine = filter_resp(
+                &self.coeff_fine,
+                wrap_freq((df - (self.fine_ch_freq[c] - fc)) / coarse_rate),
+            )
+            .norm_sqr();
+            *r = coarse * fine
+        });
+        result
+    }
+}
@@ -0,0 +1,443 @@
+use num::{
+    complex::Complex,
+    traits::{Float, FloatConst},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::{Open01, StandardNormal};
+
+use rsdsp::{cfg::DelayerCfg, oscillator::COscillator};
+
+use std::iter::Sum;
+
+use crate::{
+    cfg::RfiCfg,
+    constants::light_speed,
+    error::LdsError,
+    sample::Sample,
+    station::Station,
+    station_src::{GeneralSrcBuilder, StationSrc, SteerableSrc},
+    utils::{angle2xyz, dot},
+};
+
+fn wrap_omega<T>(omega: T) -> T
+where
+    T: Float + FloatConst,
+{
+    let two_pi = T::PI() + T::PI();
+    omega - ((omega + T::PI()) / two_pi).floor() * two_pi
+}
+
+/// `SingleTone` whose angular frequency changes by `domega_dpt` every sample
+pub struct ChirpTone<T>
+where
+    T: std::fmt::Debug + Float,
+{
+    pub osc: COscillator<T>,
+    pub domega_dpt: T,
+    pub ampl: T,
+    pub src_dir: [T; 3],
+    pub sig_len: usize,
+}
+
+impl<T> ChirpTone<T>
+where
+    T: std::fmt::Debug + Float + FloatConst,
+{
+    pub fn new<R: std::fmt::Debug>(
+        _station: &Station<R, T>,
+        az: T,
+        ze: T,
+        omega: T,
+        domega_dpt: T,
+        ampl: T,
+        sig_len: usize,
+    ) -> Self {
+        ChirpTone {
+            osc: COscillator {
+                phi: T::zero(),
+                dphi_dpt: omega,
+            },
+            domega_dpt,
+            ampl,
+            src_dir: angle2xyz(az, ze),
+            sig_len,
+        }
+    }
+}
+
+impl<R, T> StationSrc<R, T> for ChirpTone<T>
+where
+    T: std::fmt::Debug + Float + FloatConst + std::iter::Sum,
+    R: std::fmt::Debug + Sample<T>,
+{
+    fn get_sig(&mut self, station: &Station<R, T>) -> Vec<Vec<R>> {
+        let (signal, omega): (Vec<_>, Vec<_>) = (0..self.sig_len)
+            .map(|_| {
+                let omega = self.osc.dphi_dpt;
+                let x = self.osc.get() * self.ampl;
+                self.osc.dphi_dpt = wrap_omega(omega + self.domega_dpt);
+                (x, omega)
+            })
+            .unzip();
+        station
+            .ants
+            .iter()
+            .map(|a| {
+                let nx = dot(&a.pos, &self.src_dir) / light_speed() / station.dt;
+                signal
+                    .iter()
+                    .zip(omega.iter())
+                    .map(|(&x, &w)| R::from_complex(x * Complex::<T>::new(T::zero(), nx * w).exp()))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl<R, T> SteerableSrc<R, T> for ChirpTone<T>
+where
+    T: std::fmt::Debug + Float + FloatConst + std::iter::Sum,
+    R: std::fmt::Debug + Sample<T>,
+{
+    fn set_dir(&mut self, _: &Station<R, T>, az: T, ze: T) {
+        self.src_dir = angle2xyz(az, ze);
+    }
+}
+
+/// Gaussian bursts of `burst_len` samples starting with probability
+/// `prob` at every sample outside of a burst
+pub struct ImpulsiveSrc<R, T>
+where
+    R: std::fmt::Debug,
+    T: std::fmt::Debug + Float,
+{
+    pub rng: StdRng,
+    pub prob: f64,
+    pub burst_len: usize,
+    pub ampl: T,
+    /// samples left of the current burst
+    pub remaining: usize,
+    pub sig_len: usize,
+    pub sky: GeneralSrcBuilder<R, T>,
+}
+
+impl<R, T> ImpulsiveSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        station: &Station<R, T>,
+        az: T,
+        ze: T,
+        prob: f64,
+        burst_len: usize,
+        ampl: T,
+        sig_len: usize,
+        seed: u64,
+        delayer_cfg: &DelayerCfg,
+    ) -> Self {
+        ImpulsiveSrc {
+            rng: StdRng::seed_from_u64(seed),
+            prob,
+            burst_len,
+            ampl,
+            remaining: 0,
+            sig_len,
+            sky: GeneralSrcBuilder::new(
+                station,
+                az,
+                ze,
+                delayer_cfg.max_delay,
+                delayer_cfg.half_tap,
+            ),
+        }
+    }
+}
+
+impl<R, T> StationSrc<R, T> for ImpulsiveSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    fn get_sig(&mut self, _: &Station<R, T>) -> Vec<Vec<R>> {
+        let signal: Vec<_> = (0..self.sig_len)
+            .map(|_| {
+                if self.remaining == 0 && self.rng.sample::<f64, _>(Open01) < self.prob {
+                    self.remaining = self.burst_len;
+                }
+                if self.remaining > 0 {
+                    self.remaining -= 1;
+                    R::normal(&mut self.rng, self.ampl)
+                } else {
+                    R::zero()
+                }
+            })
+            .collect();
+        self.sky.build(&signal).signal
+    }
+}
+
+impl<R, T> SteerableSrc<R, T> for ImpulsiveSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    fn set_dir(&mut self, station: &Station<R, T>, az: T, ze: T) {
+        self.sky.set_dir(station, az, ze);
+    }
+}
+
+/// Tone from a direction moving by `daz_dpt`, `dze_dpt` (radians) every sample,
+/// so that the geometric delays are evaluated per sample
+pub struct MovingTone<T>
+where
+    T: std::fmt::Debug + Float,
+{
+    pub osc: COscillator<T>,
+    pub az: T,
+    pub ze: T,
+    pub daz_dpt: T,
+    pub dze_dpt: T,
+    pub ampl: T,
+    pub sig_len: usize,
+}
+
+impl<T> MovingTone<T>
+where
+    T: std::fmt::Debug + Float + FloatConst,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<R: std::fmt::Debug>(
+        _station: &Station<R, T>,
+        az: T,
+        ze: T,
+        daz_dpt: T,
+        dze_dpt: T,
+        omega: T,
+        ampl: T,
+        sig_len: usize,
+    ) -> Self {
+        MovingTone {
+            osc: COscillator {
+                phi: T::zero(),
+                dphi_dpt: omega,
+            },
+            az,
+            ze,
+            daz_dpt,
+            dze_dpt,
+            ampl,
+            sig_len,
+        }
+    }
+}
+
+impl<R, T> StationSrc<R, T> for MovingTone<T>
+where
+    T: std::fmt::Debug + Float + FloatConst + std::iter::Sum,
+    R: std::fmt::Debug + Sample<T>,
+{
+    fn get_sig(&mut self, station: &Station<R, T>) -> Vec<Vec<R>> {
+        let (signal, dirs): (Vec<_>, Vec<_>) = (0..self.sig_len)
+            .map(|_| {
+                let dir = angle2xyz(self.az, self.ze);
+                self.az = self.az + self.daz_dpt;
+                self.ze = self.ze + self.dze_dpt;
+                (self.osc.get() * self.ampl, dir)
+            })
+            .unzip();
+        let omega = self.osc.dphi_dpt;
+        station
+            .ants
+            .iter()
+            .map(|a| {
+                signal
+                    .iter()
+                    .zip(dirs.iter())
+                    .map(|(&x, dir)| {
+                        let nx = dot(&a.pos, dir) / light_speed() / station.dt;
+                        R::from_complex(x * Complex::<T>::new(T::zero(), nx * omega).exp())
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+pub fn rfi_from_cfg<R, T>(
+    station: &Station<R, T>,
+    cfg: &RfiCfg,
+    delayer_cfg: &DelayerCfg,
+    sig_len: usize,
+) -> Result<Box<dyn StationSrc<R, T>>, LdsError>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + 'static,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>
+        + 'static,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    let dt = station.dt.to_f64().unwrap();
+    let two_pi = 2.0 * std::f64::consts::PI;
+    let t = |x: f64| T::from(x).unwrap();
+    let invalid = |what: &str, x: f64, issue: &str| {
+        Err(LdsError::InvalidRfi(format!("{} = {} {}", what, x, issue)))
+    };
+    match *cfg {
+        RfiCfg::Chirp { ampl, .. } | RfiCfg::Satellite { ampl, .. } if !ampl.is_finite() => {
+            return invalid("ampl", ampl, "is not finite");
+        }
+        RfiCfg::Impulsive {
+            rate, width, ampl, ..
+        } => {
+            if !(rate >= 0.0 && rate.is_finite()) {
+                return invalid("rate", rate, "is negative or not finite");
+            }
+            if !(width > 0.0 && width.is_finite()) {
+                return invalid("width", width, "is not positive");
+            }
+            if !ampl.is_finite() {
+                return invalid("ampl", ampl, "is not finite");
+            }
+        }
+        _ => {}
+    }
+    let (az, ze) = match *cfg {
+        RfiCfg::Chirp { pos, mjd, .. }
+        | RfiCfg::Impulsive { pos, mjd, .. }
+        | RfiCfg::Satellite { pos, mjd, .. } => pos.azze(station.location.as_ref(), mjd)?,
+    };
+    let (az, ze) = (t(az), t(ze));
+    Ok(match *cfg {
+        RfiCfg::Chirp {
+            freq, drift, ampl, ..
+        } => Box::new(ChirpTone::new(
+            station,
+            az,
+            ze,
+            t(two_pi * freq),
+            t(two_pi * drift * dt),
+            t(ampl),
+            sig_len,
+        )),
+        RfiCfg::Impulsive {
+            seed,
+            rate,
+            width,
+            ampl,
+            ..
+        } => Box::new(ImpulsiveSrc::new(
+            station,
+            az,
+            ze,
+            rate * dt,
+            ((width / dt).round() as usize).max(1),
+            t(ampl),
+            sig_len,
+            seed,
+            delayer_cfg,
+        )),
+        RfiCfg::Satellite {
+            az_rate,
+            ze_rate,
+            freq,
+            ampl,
+            ..
+        } => Box::new(MovingTone::new(
+            station,
+            az,
+            ze,
+            t((az_rate * dt).to_radians()),
+            t((ze_rate * dt).to_radians()),
+            t(two_pi * freq),
+            t(ampl),
+            sig_len,
+        )),
+    })
+}
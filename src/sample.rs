@@ -0,0 +1,60 @@
+use num::{complex::Complex, traits::Float};
+
+use rand::{rngs::StdRng, Rng};
+use rand_distr::StandardNormal;
+
+/// Antenna sample type, either real voltages or complex baseband
+pub trait Sample<T>: Copy {
+    const IS_REAL: bool;
+
+    /// Drops the imaginary part of real samples
+    fn from_complex(x: Complex<T>) -> Self;
+
+    /// Zero mean normal sample with `E|x|^2 = sigma^2`
+    fn normal(rng: &mut StdRng, sigma: T) -> Self;
+
+    /// Applies `f` to the real and, if any, the imaginary part
+    fn map_parts<F: FnMut(T) -> T>(self, f: F) -> Self;
+}
+
+impl<T> Sample<T> for T
+where
+    T: Float,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    const IS_REAL: bool = true;
+
+    fn from_complex(x: Complex<T>) -> Self {
+        x.re
+    }
+
+    fn normal(rng: &mut StdRng, sigma: T) -> Self {
+        rng.sample::<T, _>(StandardNormal) * sigma
+    }
+
+    fn map_parts<F: FnMut(T) -> T>(self, mut f: F) -> Self {
+        f(self)
+    }
+}
+
+impl<T> Sample<T> for Complex<T>
+where
+    T: Float,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    const IS_REAL: bool = false;
+
+    fn from_complex(x: Complex<T>) -> Self {
+        x
+    }
+
+    fn normal(rng: &mut StdRng, sigma: T) -> Self {
+        let s = sigma / T::from(2).unwrap().sqrt();
+        Complex::<T>::new(rng.sample(StandardNormal), rng.sample(StandardNormal)) * s
+    }
+
+    fn map_parts<F: FnMut(T) -> T>(self, mut f: F) -> Self {
+        let re = f(self.re);
+        Complex::<T>::new(re, f(self.im))
+    }
+}
@@ -0,0 +1,156 @@
+use num::{
+    complex::Complex,
+    traits::{Float, FloatConst},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::{Open01, StandardNormal};
+
+use rsdsp::cfg::DelayerCfg;
+
+use rustfft::FftNum;
+
+use crate::{
+    cfg::{SkyModelCfg, SkyPos},
+    coords::{lst, radec2azze},
+    error::LdsError,
+    sample::Sample,
+    spectrum::Spectrum,
+    station::Station,
+    station_src::{BroadbandSrc, CompositeSrc, StationSrc},
+};
+
+/// Sum of independently delayed broadband components, one per catalogue entry
+/// plus those standing for the diffuse background
+pub struct SkyModelSrc<R, T>
+where
+    R: std::fmt::Debug,
+    T: std::fmt::Debug + Float,
+{
+    pub components: CompositeSrc<R, T>,
+    /// (azimuth, zenith, flux, spectrum) of the components above the horizon, angles in
+    /// radians and flux at the reference frequency
+    pub directions: Vec<(T, T, T, Spectrum)>,
+}
+
+impl<R, T> SkyModelSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + FftNum,
+    Complex<T>: From<R>,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>
+        + 'static,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    /// (lst, latitude) in radians
+    fn observer(station: &Station<R, T>, cfg: &SkyModelCfg) -> Result<(f64, f64), LdsError> {
+        match (&cfg.observer, &station.location, cfg.mjd) {
+            (Some(observer), _, _) => {
+                Ok((observer.lst.to_radians(), observer.latitude.to_radians()))
+            }
+            (None, Some(location), Some(mjd)) => Ok((
+                lst(mjd, location.longitude.to_radians()),
+                location.latitude.to_radians(),
+            )),
+            (None, Some(_), None) => Err(LdsError::NoMjd),
+            _ => Err(LdsError::NoObserver),
+        }
+    }
+
+    pub fn from_cfg(
+        station: &Station<R, T>,
+        cfg: &SkyModelCfg,
+        delayer_cfg: &DelayerCfg,
+        sig_len: usize,
+    ) -> Result<Self, LdsError> {
+        // (az, ze, flux, spectral_index) in radians
+        let mut entries = Vec::new();
+        for src in &cfg.sources {
+            let (az, ze) = match src.pos {
+                SkyPos::AzZe { az, ze } => (az.to_radians(), ze.to_radians()),
+                SkyPos::RaDec { ra, dec } => {
+                    let (lst, latitude) = Self::observer(station, cfg)?;
+                    radec2azze(ra.to_radians(), dec.to_radians(), lst, latitude)
+                }
+            };
+            entries.push((az, ze, src.flux, src.spectral_index));
+        }
+
+        if let Some(diffuse) = &cfg.diffuse {
+            let mut rng = StdRng::seed_from_u64(cfg.seed);
+            for _ in 0..diffuse.nsrc {
+                let u1: f64 = rng.sample(Open01);
+                let u2: f64 = rng.sample(Open01);
+                entries.push((
+                    2.0 * std::f64::consts::PI * u2,
+                    u1.acos(),
+                    diffuse.total_flux / diffuse.nsrc as f64,
+                    diffuse.spectral_index,
+                ));
+            }
+        }
+
+        let mut components = CompositeSrc::new();
+        let mut directions = Vec::new();
+        for (i, &(az, ze, flux, index)) in entries.iter().enumerate() {
+            if ze >= std::f64::consts::FRAC_PI_2 {
+                continue;
+            }
+            let spectrum = if index == 0.0 {
+                Spectrum::Flat
+            } else {
+                Spectrum::power_law(index, cfg.f_ref)?
+            };
+            let az = T::from(az).unwrap();
+            let ze = T::from(ze).unwrap();
+            let src = BroadbandSrc::new(
+                station,
+                &spectrum,
+                az,
+                ze,
+                cfg.ntap,
+                sig_len,
+                cfg.seed.wrapping_add(i as u64 + 1),
+                delayer_cfg,
+            )?;
+            let flux = T::from(flux).unwrap();
+            components = components.with_src(Box::new(src), flux.sqrt());
+            directions.push((az, ze, flux, spectrum));
+        }
+
+        Ok(SkyModelSrc {
+            components,
+            directions,
+        })
+    }
+}
+
+impl<R, T> StationSrc<R, T> for SkyModelSrc<R, T>
+where
+    T: std::fmt::Debug + Float,
+    R: std::fmt::Debug + Copy + std::ops::Mul<T, Output = R> + std::ops::AddAssign,
+{
+    fn get_sig(&mut self, station: &Station<R, T>) -> Vec<Vec<R>> {
+        self.components.get_sig(station)
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use num::{
+    complex::Complex,
+    traits::{Float, FloatConst},
+};
+
+use rustfft::{FftNum, FftPlanner};
+
+use rsdsp::utils::fftfreq;
+
+use crate::{cfg::SpectrumCfg, error::LdsError};
+
+#[derive(Debug, Clone)]
+pub enum Spectrum {
+    Flat,
+    PowerLaw { index: f64, f_ref: f64 },
+    Tabulated { freq: Vec<f64>, psd: Vec<f64> },
+}
+
+impl Spectrum {
+    pub fn from_cfg(cfg: &SpectrumCfg) -> Result<Self, LdsError> {
+        match cfg {
+            SpectrumCfg::Flat => Ok(Spectrum::Flat),
+            &SpectrumCfg::PowerLaw { index, f_ref } => Self::power_law(index, f_ref),
+            SpectrumCfg::Tabulated { file } => Self::load_table(file),
+        }
+    }
+
+    /// `f_ref` in Hz must be positive and finite
+    pub fn power_law(index: f64, f_ref: f64) -> Result<Self, LdsError> {
+        if f_ref > 0.0 && f_ref.is_finite() {
+            Ok(Spectrum::PowerLaw { index, f_ref })
+        } else {
+            Err(LdsError::InvalidRefFreq(f_ref))
+        }
+    }
+
+    pub fn load_table<P: AsRef<Path>>(path: P) -> Result<Self, LdsError> {
+        let path = path.as_ref();
+        let invalid = |reason: String| LdsError::InvalidSpectrum {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let content = std::fs::read_to_string(path).map_err(|source| LdsError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let mut table = Vec::new();
+        for (n, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let cols = line
+                .split_whitespace()
+                .map(|x| x.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| invalid(format!("line {}: {}", n + 1, e)))?;
+            if cols.len() != 2 {
+                return Err(invalid(format!("line {}: expect 2 columns", n + 1)));
+            }
+            table.push((cols[0], cols[1]));
+        }
+        if table.is_empty() {
+            return Err(invalid("no entry".to_string()));
+        }
+        table.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (freq, psd) = table.into_iter().unzip();
+        Ok(Spectrum::Tabulated { freq, psd })
+    }
+
+    pub fn requires_sky_freq(&self) -> bool {
+        !matches!(self, Spectrum::Flat)
+    }
+
+    /// `f` is the sky frequency in Hz, ignored for a flat spectrum; a power law has
+    /// no power at or below 0 Hz, where it would diverge for a negative index
+    pub fn psd(&self, f: f64) -> f64 {
+        match self {
+            Spectrum::Flat => 1.0,
+            &Spectrum::PowerLaw { .. } if f <= 0.0 => 0.0,
+            &Spectrum::PowerLaw { index, f_ref } => (f / f_ref).powf(index),
+            Spectrum::Tabulated { freq, psd } => {
+                let i = freq.partition_point(|&f1| f1 < f);
+                if i == 0 {
+                    psd[0]
+                } else if i == freq.len() {
+                    psd[freq.len() - 1]
+                } else {
+                    let w = (f - freq[i - 1]) / (freq[i] - freq[i - 1]);
+                    psd[i - 1] * (1.0 - w) + psd[i] * w
+                }
+            }
+        }
+    }
+}
+
+/// FIR filter turning unit-variance white noise into noise with the given psd,
+/// where `freq_in_hz` maps normalized frequency to sky frequency
+pub fn shaping_filter<T, F>(spectrum: &Spectrum, ntap: usize, freq_in_hz: F) -> Vec<Complex<T>>
+where
+    T: Float + FloatConst + FftNum,
+    F: Fn(T) -> T,
+{
+    assert!(ntap > 0);
+    let mut h: Vec<_> = fftfreq::<T>(ntap)
+        .into_iter()
+        .map(|f| {
+            let psd = spectrum.psd(freq_in_hz(f).to_f64().unwrap());
+            Complex::<T>::new(T::from(psd.max(0.0).sqrt()).unwrap(), T::zero())
+        })
+        .collect();
+    let mean_psd = h.iter().fold(T::zero(), |a, x| a + x.norm_sqr()) / T::from(ntap).unwrap();
+    FftPlanner::<T>::new()
+        .plan_fft_inverse(ntap)
+        .process(&mut h);
+
+    // centre the impulse response and taper it with a Hann window
+    h.rotate_right(ntap / 2);
+    let two = T::one() + T::one();
+    for (i, x) in h.iter_mut().enumerate() {
+        let w = (T::PI() * (T::from(i).unwrap() + T::one() / two) / T::from(ntap).unwrap())
+            .sin()
+            .powi(2);
+        *x = *x * w;
+    }
+
+    // unit-variance input then comes out with the mean psd as its power
+    let power = h.iter().fold(T::zero(), |a, x| a + x.norm_sqr());
+    let scale = if power > T::zero() {
+        (mean_psd / power).sqrt()
+    } else {
+        T::zero()
+    };
+    h.iter().map(|&x| x * scale).collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct FirFilter<T> {
+    pub coeff: Vec<Complex<T>>,
+    history: Vec<Complex<T>>,
+}
+
+impl<T> FirFilter<T>
+where
+    T: Float,
+{
+    pub fn new(coeff: Vec<Complex<T>>) -> Self {
+        let history = vec![Complex::<T>::new(T::zero(), T::zero()); coeff.len() - 1];
+        FirFilter { coeff, history }
+    }
+
+    pub fn filter(&mut self, x: &[Complex<T>]) -> Vec<Complex<T>> {
+        let ntap = self.coeff.len();
+        let mut buf = std::mem::take(&mut self.history);
+        buf.extend_from_slice(x);
+        let y = (0..x.len())
+            .map(|n| {
+                self.coeff
+                    .iter()
+                    .rev()
+                    .zip(buf[n..n + ntap].iter())
+                    .fold(Complex::<T>::new(T::zero(), T::zero()), |a, (&c, &x1)| {
+                        a + c * x1
+                    })
+            })
+            .collect();
+        self.history = buf.split_off(buf.len() - (ntap - 1));
+        y
+    }
+}
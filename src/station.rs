@@ -1,6 +1,9 @@
-use ndarray::{Array2, ScalarOperand};
+use ndarray::{parallel::prelude::*, s, stack, Array2, Array3, ArrayViewMut2, Axis, ScalarOperand};
 
-use std::{iter::Sum, ops::Mul};
+use std::{
+    iter::Sum,
+    ops::{Mul, Range},
+};
 
 use num::{
     complex::Complex,
@@ -12,15 +15,30 @@ use rsdsp::{
 };
 
 use crate::{
-    cfg::StationCfg,
+    cfg::{LocationCfg, RequantCfg, SkyFreqCfg, StationCfg},
     constants::light_speed,
+    coords::{lst, radec2azze},
+    digitizer::{Digitizer, DigitizerStats},
+    error::LdsError,
+    requant::{RequantStages, Requantizer},
+    sample::Sample,
     station_src::StationSrc,
+    stream::{AcquireStream, Beamforming},
     utils::{angle2xyz, apply_delay, dot},
+    weights::{BeamWeights, DelayTracker},
 };
 
 use rustfft::FftNum;
 
-#[derive(Debug)]
+/// Antennas channelized at a time when beamforming, bounding the memory held
+const ANT_CHUNK: usize = 16;
+
+/// Coarse and fine channels of one beam
+pub type BeamData<T> = (Array2<Complex<T>>, Array2<Complex<T>>);
+/// Coarse and fine channels stacked over the beams
+pub type MultiBeamData<T> = (Array3<Complex<T>>, Array3<Complex<T>>);
+
+#[derive(Debug, Clone)]
 pub struct Antenna<R, T>
 where
     T: std::fmt::Debug + Float,
@@ -86,7 +104,7 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Station<R, T>
 where
     T: std::fmt::Debug + Float,
@@ -96,6 +114,25 @@ where
     pub ants: Vec<Antenna<R, T>>,
     //synthesizer: Synthesizer<f64, f64>,
     pub csp_pfb: CspPfb<T>,
+    pub coeff_stage1: Vec<T>,
+    pub coeff_stage2: Vec<T>,
+    /// fine PFBs of the configured beams, each with its own coarse channel subset
+    pub beam_pfbs: Vec<CspPfb<T>>,
+    /// fine PFBs of ad-hoc multi-beam acquisition, all on `csp_pfb`'s channels
+    pub multi_pfbs: Vec<CspPfb<T>>,
+    pub beam_pointings: Vec<(T, T)>,
+    pub sky_freq: Option<SkyFreqCfg>,
+    pub location: Option<LocationCfg>,
+    pub requant: RequantStages<T>,
+    /// fine requantization of each configured beam, with that beam's own gains
+    pub beam_requant: Vec<Option<Requantizer<T>>>,
+    /// fine requantization of each ad-hoc beam, copied from `requant.fine`
+    pub multi_requant: Vec<Option<Requantizer<T>>>,
+    /// complex gains of the antenna errors, applied to the coarse channels
+    pub ant_errors: Option<BeamWeights<T>>,
+    /// ADCs quantizing every antenna signal before the coarse PFB
+    pub digitizer: Option<Digitizer<T>>,
+    pub digitizer_stats: DigitizerStats,
 }
 
 impl<R, T> Station<R, T>
@@ -119,7 +156,8 @@ where
         + std::fmt::Debug
         + Sum
         + Sync
-        + Send,
+        + Send
+        + Sample<T>,
 {
     pub fn new(
         pos: &[[T; 3]],
@@ -145,13 +183,141 @@ where
         let fine_pfb = CsPfb::<Complex<T>, T>::new(nfine_ch * 2, coeff_stage2);
         let csp_pfb = CspPfb::new(coarse_ch_selected, &fine_pfb);
 
-        Station { ants, dt, csp_pfb }
+        Station {
+            ants,
+            dt,
+            csp_pfb,
+            coeff_stage1: coeff_stage1.to_vec(),
+            coeff_stage2: coeff_stage2.to_vec(),
+            beam_pfbs: vec![],
+            multi_pfbs: vec![],
+            beam_pointings: vec![],
+            sky_freq: None,
+            location: None,
+            requant: RequantStages::default(),
+            beam_requant: vec![],
+            multi_requant: vec![],
+            ant_errors: None,
+            digitizer: None,
+            digitizer_stats: DigitizerStats::new(pos.len()),
+        }
+    }
+
+    fn new_csp_pfb(&self, coarse_ch_selected: &[usize]) -> CspPfb<T> {
+        let fine_pfb =
+            CsPfb::<Complex<T>, T>::new(self.csp_pfb.nfine_per_coarse() * 2, &self.coeff_stage2);
+        CspPfb::new(coarse_ch_selected, &fine_pfb)
+    }
+
+    pub fn reset(&mut self) {
+        let ncoarse_ch = self.ncoarse_ch();
+        for ant in self.ants.iter_mut() {
+            ant.channelizer = OsPfb::new(ncoarse_ch, &self.coeff_stage1);
+        }
+        self.csp_pfb = self.new_csp_pfb(&self.csp_pfb.coarse_ch_selected);
+        self.beam_pfbs = self
+            .beam_pfbs
+            .iter()
+            .map(|p| self.new_csp_pfb(&p.coarse_ch_selected))
+            .collect();
+        self.multi_pfbs = self
+            .multi_pfbs
+            .iter()
+            .map(|p| self.new_csp_pfb(&p.coarse_ch_selected))
+            .collect();
+        self.digitizer_stats = DigitizerStats::new(self.ants.len());
+        let requants = [
+            &mut self.requant.coarse,
+            &mut self.requant.beam,
+            &mut self.requant.fine,
+        ]
+        .into_iter()
+        .chain(self.beam_requant.iter_mut())
+        .chain(self.multi_requant.iter_mut());
+        for requant in requants.flatten() {
+            requant.nsamples = 0;
+            requant.nclipped = 0;
+        }
+    }
+
+    /// Pointings are azimuth and zenith in radians
+    pub fn with_beams(mut self, beams: &[((T, T), Vec<usize>)]) -> Self {
+        self.beam_pfbs = beams.iter().map(|(_, ch)| self.new_csp_pfb(ch)).collect();
+        self.beam_pointings = beams.iter().map(|&(p, _)| p).collect();
+        self.beam_requant = vec![None; beams.len()];
+        self
+    }
+
+    pub fn ensure_nbeams(&mut self, nbeams: usize) {
+        while self.multi_pfbs.len() < nbeams {
+            let csp_pfb = self.new_csp_pfb(&self.csp_pfb.coarse_ch_selected);
+            self.multi_pfbs.push(csp_pfb);
+            let requant = self.requant.fine.clone().map(Requantizer::with_zero_stats);
+            self.multi_requant.push(requant);
+        }
+    }
+
+    pub fn with_sky_freq(mut self, sky_freq: Option<SkyFreqCfg>) -> Self {
+        self.sky_freq = sky_freq;
+        self
+    }
+
+    pub fn with_requant(mut self, requant: RequantStages<T>) -> Self {
+        self.requant = requant;
+        self
+    }
+
+    /// One entry per configured beam, so call after `with_beams`
+    pub fn with_beam_requant(mut self, beam_requant: Vec<Option<Requantizer<T>>>) -> Self {
+        assert_eq!(beam_requant.len(), self.beam_pfbs.len());
+        self.beam_requant = beam_requant;
+        self
+    }
+
+    pub fn with_digitizer(mut self, digitizer: Option<Digitizer<T>>) -> Self {
+        self.digitizer = digitizer;
+        self
+    }
+
+    pub fn with_ant_errors(mut self, ant_errors: Option<BeamWeights<T>>) -> Self {
+        if let Some(e) = &ant_errors {
+            assert_eq!(e.nant(), self.ants.len());
+        }
+        self.ant_errors = ant_errors;
+        self
+    }
+
+    fn requant_beam(&mut self, x: ArrayViewMut2<Complex<T>>) {
+        if let Some(requant) = self.requant.beam.as_mut() {
+            requant.apply(x);
+        }
+    }
+
+    fn requant_fine(&mut self, x: Array2<Complex<T>>) -> Array2<Complex<T>> {
+        requant_with(self.requant.fine.as_mut(), x)
+    }
+
+    pub fn with_location(mut self, location: Option<LocationCfg>) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// (azimuth, zenith) in radians of `ra`, `dec` (radians) seen from the station at `mjd`
+    pub fn radec2azze(&self, ra: T, dec: T, mjd: f64) -> Result<(T, T), LdsError> {
+        let location = self.location.as_ref().ok_or(LdsError::NoLocation)?;
+        let lst = T::from(lst(mjd, location.longitude.to_radians())).unwrap();
+        let latitude = T::from(location.latitude.to_radians()).unwrap();
+        Ok(radec2azze(ra, dec, lst, latitude))
     }
 
     pub fn ncoarse_ch(&self) -> usize {
         self.ants[0].channelizer.nch_total()
     }
 
+    pub fn delay_weights(&self, digital_delay: &[T]) -> BeamWeights<T> {
+        BeamWeights::from_delay(digital_delay, self.ncoarse_ch())
+    }
+
     pub fn calc_required_digital_delay(&self, azimuth: T, zenith: T) -> Vec<T> {
         let dc = angle2xyz(azimuth, zenith);
         self.ants
@@ -167,6 +333,8 @@ where
             .collect()
     }
 
+    /// Channels above `(ncoarse_ch - 1) / 2` hold negative frequencies, which are the
+    /// conjugate images of the positive ones for real samples
     pub fn coarse_ch_freq_in_fs(&self, ch: &[usize]) -> Vec<T> {
         let ncoarse_ch = self.ants[0].channelizer.nch_total();
         let coase_ch_spacing = T::from(1).unwrap() / T::from(ncoarse_ch).unwrap();
@@ -183,12 +351,28 @@ where
     }
 
     pub fn fine_ch_freq_in_fs(&self) -> Vec<T> {
-        let nfine_per_coarse = self.csp_pfb.nfine_per_coarse();
+        self.fine_ch_freq_of(&self.csp_pfb)
+    }
+
+    pub fn coarse_freq_of_fine_ch_in_fs(&self) -> Vec<T> {
+        self.coarse_freq_of_fine_ch_of(&self.csp_pfb)
+    }
+
+    pub fn beam_fine_ch_freq_in_fs(&self, beam: usize) -> Vec<T> {
+        self.fine_ch_freq_of(&self.beam_pfbs[beam])
+    }
+
+    pub fn beam_coarse_freq_of_fine_ch_in_fs(&self, beam: usize) -> Vec<T> {
+        self.coarse_freq_of_fine_ch_of(&self.beam_pfbs[beam])
+    }
+
+    fn fine_ch_freq_of(&self, csp_pfb: &CspPfb<T>) -> Vec<T> {
+        let nfine_per_coarse = csp_pfb.nfine_per_coarse();
         let ncoarse_ch = self.ants[0].channelizer.nch_total();
         let coarse_ch_spacing = T::from(1).unwrap() / T::from(ncoarse_ch).unwrap();
         let mut result = vec![];
 
-        for fc in self.coarse_ch_freq_in_fs(&self.csp_pfb.coarse_ch_selected) {
+        for fc in self.coarse_ch_freq_in_fs(&csp_pfb.coarse_ch_selected) {
             for f in 0..nfine_per_coarse {
                 result.push(
                     fc + (T::from(f).unwrap()
@@ -201,10 +385,10 @@ where
         result
     }
 
-    pub fn coarse_freq_of_fine_ch_in_fs(&self) -> Vec<T> {
-        let nfine_per_coarse = self.csp_pfb.nfine_per_coarse();
+    fn coarse_freq_of_fine_ch_of(&self, csp_pfb: &CspPfb<T>) -> Vec<T> {
+        let nfine_per_coarse = csp_pfb.nfine_per_coarse();
         let mut result = vec![];
-        for fc in self.coarse_ch_freq_in_fs(&self.csp_pfb.coarse_ch_selected) {
+        for fc in self.coarse_ch_freq_in_fs(&csp_pfb.coarse_ch_selected) {
             for _ in 0..nfine_per_coarse {
                 result.push(fc);
             }
@@ -255,24 +439,31 @@ where
         self.gain(&f, &fc, az, ze, az0, ze0)
     }
 
+    fn check_delay_len(&self, digital_delay: &[T]) -> Result<(), LdsError> {
+        if digital_delay.len() == self.ants.len() {
+            Ok(())
+        } else {
+            Err(LdsError::WrongAntCount {
+                what: "delay",
+                len: digital_delay.len(),
+                nant: self.ants.len(),
+            })
+        }
+    }
+
     pub fn acquire(
         &mut self,
         src: &mut dyn StationSrc<R, T>,
         digital_delay: &[T],
-    ) -> Array2<Complex<T>> {
+    ) -> Result<Array2<Complex<T>>, LdsError> {
+        self.check_delay_len(digital_delay)?;
         //let src_dir=angle2xyz(azimuth, zenith);
         let signal = src.get_sig(self);
-        self.ants
-            .iter_mut()
-            .zip(signal)
-            .zip(digital_delay.iter())
-            .map(|((ant, signal1), &d)| {
-                let mut channelized = ant.acquire(&signal1);
-                apply_delay(&mut channelized, d);
-                channelized
-            })
-            .reduce(|a, b| a + b)
-            .unwrap()
+        let mut result = self.beamform(&signal, |_| {
+            |i, x: &mut Array2<Complex<T>>| apply_delay(x, digital_delay[i])
+        });
+        self.requant_beam(result.view_mut());
+        Ok(result)
         //self.synthesizer.synthesize(result.view())
     }
 
@@ -280,9 +471,322 @@ where
         &mut self,
         src: &mut dyn StationSrc<R, T>,
         digital_delay: &[T],
+    ) -> Result<BeamData<T>, LdsError> {
+        let coarse_data = self.acquire(src, digital_delay)?;
+        let fine_data = self.csp_pfb.analyze(coarse_data.view());
+        let fine_data = self.requant_fine(fine_data);
+        Ok((coarse_data, fine_data))
+    }
+
+    pub fn acquire_multi(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        digital_delays: &[Vec<T>],
+    ) -> Result<Array3<Complex<T>>, LdsError> {
+        let weights = digital_delays
+            .iter()
+            .map(|d| {
+                self.check_delay_len(d)?;
+                Ok(self.delay_weights(d))
+            })
+            .collect::<Result<Vec<_>, LdsError>>()?;
+        Ok(self.acquire_multi_weighted(src, &weights))
+    }
+
+    pub fn acquire_multi_weighted(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        weights: &[BeamWeights<T>],
+    ) -> Array3<Complex<T>> {
+        weights
+            .iter()
+            .for_each(|w| assert_eq!(w.nant(), self.ants.len()));
+        let signal = src.get_sig(self);
+        let nant = self.ants.len();
+        let mut result: Option<Array3<Complex<T>>> = None;
+        for first in (0..nant).step_by(ANT_CHUNK) {
+            let channelized = self.channelize_range(&signal, first..nant.min(first + ANT_CHUNK));
+            let result = result.get_or_insert_with(|| {
+                Array3::zeros((
+                    weights.len(),
+                    channelized[0].nrows(),
+                    channelized[0].ncols(),
+                ))
+            });
+            result
+                .outer_iter_mut()
+                .into_par_iter()
+                .zip(weights.par_iter())
+                .for_each(|(mut beam, w)| {
+                    for (i, x) in channelized.iter().enumerate() {
+                        for ((mut r, x), &k) in beam
+                            .rows_mut()
+                            .into_iter()
+                            .zip(x.rows())
+                            .zip(w.w.row(first + i).iter())
+                        {
+                            r.zip_mut_with(&x, |r1, &x1| *r1 += x1 * k);
+                        }
+                    }
+                });
+        }
+        let mut result = result.unwrap();
+        for beam in result.outer_iter_mut() {
+            self.requant_beam(beam);
+        }
+        result
+    }
+
+    pub fn acquire_fine_multi(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        digital_delays: &[Vec<T>],
+    ) -> Result<MultiBeamData<T>, LdsError> {
+        let weights = digital_delays
+            .iter()
+            .map(|d| {
+                self.check_delay_len(d)?;
+                Ok(self.delay_weights(d))
+            })
+            .collect::<Result<Vec<_>, LdsError>>()?;
+        Ok(self.acquire_fine_multi_weighted(src, &weights))
+    }
+
+    pub fn acquire_fine_multi_weighted(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        weights: &[BeamWeights<T>],
+    ) -> (Array3<Complex<T>>, Array3<Complex<T>>) {
+        self.ensure_nbeams(weights.len());
+        let coarse_data = self.acquire_multi_weighted(src, weights);
+        let fine_data: Vec<_> = self
+            .multi_pfbs
+            .iter_mut()
+            .zip(coarse_data.outer_iter())
+            .map(|(csp_pfb, coarse1)| csp_pfb.analyze(coarse1))
+            .collect();
+        let fine_data: Vec<_> = fine_data
+            .into_iter()
+            .zip(self.multi_requant.iter_mut())
+            .map(|(x, requant)| requant_with(requant.as_mut(), x))
+            .collect();
+        let fine_data = stack(
+            Axis(0),
+            &fine_data.iter().map(|x| x.view()).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        (coarse_data, fine_data)
+    }
+
+    /// Fine channels of each configured beam cover that beam's own coarse channel subset,
+    /// `weights` has one entry per configured beam
+    pub fn acquire_fine_beams_weighted(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        weights: &[BeamWeights<T>],
+    ) -> (Array3<Complex<T>>, Vec<Array2<Complex<T>>>) {
+        assert_eq!(weights.len(), self.beam_pfbs.len());
+        let coarse_data = self.acquire_multi_weighted(src, weights);
+        let fine_data: Vec<_> = self
+            .beam_pfbs
+            .iter_mut()
+            .zip(coarse_data.outer_iter())
+            .map(|(csp_pfb, coarse1)| csp_pfb.analyze(coarse1))
+            .collect();
+        let fine_data = fine_data
+            .into_iter()
+            .zip(self.beam_requant.iter_mut())
+            .map(|(x, requant)| requant_with(requant.as_mut(), x))
+            .collect();
+        (coarse_data, fine_data)
+    }
+
+    pub fn acquire_fine_beams(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+    ) -> (Array3<Complex<T>>, Vec<Array2<Complex<T>>>) {
+        let weights: Vec<_> = self
+            .beam_pointings
+            .iter()
+            .map(|&(az, ze)| self.delay_weights(&self.calc_required_digital_delay(az, ze)))
+            .collect();
+        self.acquire_fine_beams_weighted(src, &weights)
+    }
+
+    /// Antennas are channelized in parallel, the output keeps the antenna order
+    pub fn channelize(&mut self, signal: &[Vec<R>]) -> Vec<Array2<Complex<T>>> {
+        self.channelize_range(signal, 0..self.ants.len())
+    }
+
+    fn channelize_range(
+        &mut self,
+        signal: &[Vec<R>],
+        ants: Range<usize>,
+    ) -> Vec<Array2<Complex<T>>> {
+        let ant_errors = self.ant_errors.as_ref();
+        let digitizer = self.digitizer.as_ref();
+        let first = ants.start;
+        let (mut result, blocks): (Vec<_>, Vec<_>) = self.ants[ants.clone()]
+            .par_iter_mut()
+            .zip(signal[ants].par_iter())
+            .enumerate()
+            .map(|(i, (ant, signal1))| {
+                let (mut x, block) = match digitizer {
+                    Some(d) => {
+                        let mut signal1 = signal1.clone();
+                        let block = d.digitize(&mut signal1);
+                        (ant.acquire(&signal1), Some(block))
+                    }
+                    None => (ant.acquire(signal1), None),
+                };
+                // narrowband errors, see `AntErrorCfg`
+                if let Some(e) = ant_errors {
+                    e.apply(first + i, &mut x);
+                }
+                (x, block)
+            })
+            .unzip();
+        for (i, block) in blocks.iter().enumerate() {
+            if let Some(block) = block {
+                self.digitizer_stats.add(first + i, block);
+            }
+        }
+        if let Some(requant) = self.requant.coarse.as_mut() {
+            for x in result.iter_mut() {
+                requant.apply(x.view_mut());
+            }
+        }
+        result
+    }
+
+    /// Sum over the antennas, channelizing `ANT_CHUNK` antennas at a time and adding them
+    /// in antenna order so that the result does not depend on the thread count; `weight`
+    /// gets the number of coarse samples and returns the weighting of (antenna, channels)
+    fn beamform<F, W>(&mut self, signal: &[Vec<R>], weight: F) -> Array2<Complex<T>>
+    where
+        F: FnOnce(usize) -> W,
+        W: Fn(usize, &mut Array2<Complex<T>>) + Sync,
+    {
+        let nant = self.ants.len();
+        let mut weight = Some(weight);
+        let mut w = None;
+        let mut result: Option<Array2<Complex<T>>> = None;
+        for first in (0..nant).step_by(ANT_CHUNK) {
+            let mut channelized = self.channelize_range(signal, first..nant.min(first + ANT_CHUNK));
+            let w = w.get_or_insert_with(|| (weight.take().unwrap())(channelized[0].ncols()));
+            channelized
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| w(first + i, x));
+            for x in channelized {
+                result = Some(match result {
+                    Some(r) => r + x,
+                    None => x,
+                });
+            }
+        }
+        result.unwrap()
+    }
+
+    pub fn acquire_weighted(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        weights: &BeamWeights<T>,
+    ) -> Array2<Complex<T>> {
+        assert_eq!(weights.nant(), self.ants.len());
+        let signal = src.get_sig(self);
+        let mut result = self.beamform(&signal, |_| {
+            |i, x: &mut Array2<Complex<T>>| weights.apply(i, x)
+        });
+        self.requant_beam(result.view_mut());
+        result
+    }
+
+    /// Like `acquire`, but the delays follow `tracker`, stepping at its update boundaries
+    pub fn acquire_tracking(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        tracker: &mut DelayTracker<T>,
+    ) -> Array2<Complex<T>> {
+        let signal = src.get_sig(self);
+        let ncoarse_ch = self.ncoarse_ch();
+        let mut result = self.beamform(&signal, |nt| {
+            let mut segments = Vec::new();
+            let mut t1 = 0;
+            while t1 < nt {
+                let t2 = nt.min(t1 + tracker.remaining());
+                segments.push((t1..t2, BeamWeights::from_delay(&tracker.delay, ncoarse_ch)));
+                tracker.advance(t2 - t1);
+                t1 = t2;
+            }
+            move |i, x: &mut Array2<Complex<T>>| {
+                for (t, weights) in &segments {
+                    let mut x1 = x.slice(s![.., t.clone()]).to_owned();
+                    weights.apply(i, &mut x1);
+                    x.slice_mut(s![.., t.clone()]).assign(&x1);
+                }
+            }
+        });
+        self.requant_beam(result.view_mut());
+        result
+    }
+
+    pub fn acquire_fine_tracking(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        tracker: &mut DelayTracker<T>,
+    ) -> (Array2<Complex<T>>, Array2<Complex<T>>) {
+        let coarse_data = self.acquire_tracking(src, tracker);
+        let fine_data = self.csp_pfb.analyze(coarse_data.view());
+        let fine_data = self.requant_fine(fine_data);
+        (coarse_data, fine_data)
+    }
+
+    /// Tracker of the delays towards `dir(t)` (radians), `t` in coarse samples
+    pub fn delay_tracker(
+        &self,
+        update_every: usize,
+        mut dir: Box<dyn FnMut(usize) -> (T, T)>,
+    ) -> Result<DelayTracker<T>, LdsError>
+    where
+        T: 'static,
+    {
+        let pos: Vec<_> = self.ants.iter().map(|a| a.pos).collect();
+        let dt = self.dt;
+        DelayTracker::new(
+            update_every,
+            Box::new(move |t| {
+                let (az, ze) = dir(t);
+                let dc = angle2xyz(az, ze);
+                pos.iter()
+                    .map(|p| dot(&dc, p) / light_speed() / dt)
+                    .collect()
+            }),
+        )
+    }
+
+    /// Blocks of `acquire_fine_weighted` or `acquire_fine_tracking`, one per `get_sig` call
+    pub fn stream<'a>(
+        &'a mut self,
+        src: &'a mut dyn StationSrc<R, T>,
+        beamforming: Beamforming<T>,
+    ) -> AcquireStream<'a, R, T> {
+        AcquireStream::new(self, src, beamforming)
+    }
+
+    /// Sampling period of the oversampled coarse channels in units of `dt`
+    pub fn coarse_sample_interval(&self) -> usize {
+        self.ncoarse_ch() / 2
+    }
+
+    pub fn acquire_fine_weighted(
+        &mut self,
+        src: &mut dyn StationSrc<R, T>,
+        weights: &BeamWeights<T>,
     ) -> (Array2<Complex<T>>, Array2<Complex<T>>) {
-        let coarse_data = self.acquire(src, digital_delay);
+        let coarse_data = self.acquire_weighted(src, weights);
         let fine_data = self.csp_pfb.analyze(coarse_data.view());
+        let fine_data = self.requant_fine(fine_data);
         (coarse_data, fine_data)
     }
 }
@@ -308,59 +812,180 @@ where
         + std::fmt::Debug
         + Sum
         + Sync
-        + Send,
+        + Send
+        + Sample<T>,
+{
+    /// With real samples the negative frequencies mirror the positive ones
+    pub fn freq_in_hz(&self, f_in_fs: &[T]) -> Result<Vec<T>, LdsError> {
+        let sky_freq = self.sky_freq.as_ref().ok_or(LdsError::NoSkyFreq)?;
+        Ok(f_in_fs
+            .iter()
+            .map(|&f| {
+                let f = if R::IS_REAL { f.abs() } else { f };
+                sky_freq.freq_in_hz(f, self.dt)
+            })
+            .collect())
+    }
+
+    /// With real samples a channel above `ncoarse_ch / 2` holds the conjugate image of
+    /// channel `ncoarse_ch - c`, which is returned in its place
+    pub fn positive_coarse_ch(&self, c: usize) -> usize {
+        let ncoarse_ch = self.ncoarse_ch();
+        if R::IS_REAL && c > ncoarse_ch / 2 {
+            ncoarse_ch - c
+        } else {
+            c
+        }
+    }
+
+    pub fn coarse_ch_freq_in_hz(&self, ch: &[usize]) -> Result<Vec<T>, LdsError> {
+        let ch: Vec<_> = ch.iter().map(|&c| self.positive_coarse_ch(c)).collect();
+        self.freq_in_hz(&self.coarse_ch_freq_in_fs(&ch))
+    }
+
+    pub fn fine_ch_freq_in_hz(&self) -> Result<Vec<T>, LdsError> {
+        self.freq_in_hz(&self.fine_ch_freq_in_fs())
+    }
+
+    pub fn coarse_freq_of_fine_ch_in_hz(&self) -> Result<Vec<T>, LdsError> {
+        self.freq_in_hz(&self.coarse_freq_of_fine_ch_in_fs())
+    }
+}
+
+impl<R, T> Station<R, T>
+where
+    T: Float
+        + ScalarOperand
+        + FloatConst
+        + NumAssign
+        + std::iter::Sum
+        + std::marker::Send
+        + std::marker::Sync
+        + FftNum
+        + Default
+        + std::fmt::Debug,
+    Complex<T>: Copy + std::convert::From<R> + Sum + Default + ScalarOperand,
+    R: Copy
+        + Mul<T, Output = R>
+        + Default
+        + ScalarOperand
+        + NumAssign
+        + std::fmt::Debug
+        + Sum
+        + Sync
+        + Send
+        + Sample<T>,
 {
     pub fn from_cfg(cfg: &StationCfg) -> Self {
+        Self::try_from_cfg(cfg).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_cfg(cfg: &StationCfg) -> Result<Self, LdsError> {
+        cfg.validate()?;
+
         let coeff_coarse = pfb_coeff::<T>(
             cfg.coarse_pfb.nch / 2,
             cfg.coarse_pfb.tap_per_ch,
-            T::from(cfg.coarse_pfb.k).unwrap(),
+            T::from(cfg.coarse_pfb.k).ok_or(LdsError::NumericConversion("coarse_pfb.k"))?,
         )
         .into_raw_vec();
 
         let coeff_fine = pfb_coeff::<T>(
             cfg.fine_pfb.nch * 2,
             cfg.fine_pfb.tap_per_ch,
-            T::from(cfg.fine_pfb.k).unwrap(),
+            T::from(cfg.fine_pfb.k).ok_or(LdsError::NumericConversion("fine_pfb.k"))?,
         )
         .into_raw_vec();
 
-        let mut coarse_ch_selected = Vec::new();
-        cfg.selected_coarse_ch.iter().for_each(|&(c1, c2)| {
-            (c1..c2).for_each(|c| {
-                coarse_ch_selected.push(c);
+        let coarse_ch_selected = cfg.coarse_ch_selected();
+        let beams = cfg
+            .beams
+            .iter()
+            .map(|b| {
+                let (az, ze) = b.pos.azze(cfg.location.as_ref(), cfg.mjd)?;
+                let az = T::from(az).ok_or(LdsError::NumericConversion("beam az"))?;
+                let ze = T::from(ze).ok_or(LdsError::NumericConversion("beam ze"))?;
+                Ok(((az, ze), b.coarse_ch_selected()))
+            })
+            .collect::<Result<Vec<_>, LdsError>>()?;
+
+        if cfg.coarse_pfb.tap_per_ch * cfg.coarse_pfb.nch / 2 != coeff_coarse.len() {
+            return Err(LdsError::InvalidPfbGeometry {
+                stage: "coarse",
+                reason: format!(
+                    "expected {} coefficients, got {}",
+                    cfg.coarse_pfb.tap_per_ch * cfg.coarse_pfb.nch / 2,
+                    coeff_coarse.len()
+                ),
             });
-        });
-
-        assert_eq!(
-            cfg.coarse_pfb.tap_per_ch * cfg.coarse_pfb.nch / 2,
-            coeff_coarse.len()
-        );
-        assert_eq!(
-            cfg.fine_pfb.tap_per_ch * cfg.fine_pfb.nch * 2,
-            coeff_fine.len()
-        );
+        }
+        if cfg.fine_pfb.tap_per_ch * cfg.fine_pfb.nch * 2 != coeff_fine.len() {
+            return Err(LdsError::InvalidPfbGeometry {
+                stage: "fine",
+                reason: format!(
+                    "expected {} coefficients, got {}",
+                    cfg.fine_pfb.tap_per_ch * cfg.fine_pfb.nch * 2,
+                    coeff_fine.len()
+                ),
+            });
+        }
 
-        let pos: Vec<_> = cfg
+        let pos = cfg
             .pos
             .iter()
             .map(|x| {
-                [
-                    T::from(x[0]).unwrap(),
-                    T::from(x[1]).unwrap(),
-                    T::from(x[2]).unwrap(),
-                ]
+                let mut p = [T::zero(); 3];
+                for (p1, &x1) in p.iter_mut().zip(x.iter()) {
+                    *p1 = T::from(x1).ok_or(LdsError::NumericConversion("pos"))?;
+                }
+                Ok(p)
             })
-            .collect();
+            .collect::<Result<Vec<_>, LdsError>>()?;
 
-        Station::new(
+        let station = Station::new(
             &pos,
             cfg.coarse_pfb.nch,
             &coeff_coarse,
             cfg.fine_pfb.nch,
             &coeff_fine,
             &coarse_ch_selected,
-            T::from(cfg.dt).unwrap(),
-        )
+            T::from(cfg.dt).ok_or(LdsError::NumericConversion("dt"))?,
+        );
+        let ant_errors = cfg.ant_errors.as_ref().map(|e| {
+            let freqs = station.coarse_ch_freq_in_fs(&(0..cfg.coarse_pfb.nch).collect::<Vec<_>>());
+            BeamWeights::from_ant_errors(e, pos.len(), &freqs, R::IS_REAL)
+        });
+
+        let beam_requant = cfg
+            .beams
+            .iter()
+            .map(|b| {
+                cfg.requant.fine.as_ref().map(|fine| {
+                    Requantizer::from_cfg(&RequantCfg {
+                        gain: b.fine_gain.clone(),
+                        ..fine.clone()
+                    })
+                })
+            })
+            .collect();
+
+        Ok(station
+            .with_ant_errors(ant_errors)
+            .with_sky_freq(cfg.sky_freq.clone())
+            .with_location(cfg.location)
+            .with_requant(RequantStages::from_cfg(&cfg.requant))
+            .with_digitizer(cfg.digitizer.as_ref().map(Digitizer::from_cfg))
+            .with_beams(&beams)
+            .with_beam_requant(beam_requant))
+    }
+}
+
+fn requant_with<T: Float>(
+    requant: Option<&mut Requantizer<T>>,
+    mut x: Array2<Complex<T>>,
+) -> Array2<Complex<T>> {
+    if let Some(requant) = requant {
+        requant.apply(x.view_mut());
     }
+    x
 }
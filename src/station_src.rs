@@ -3,11 +3,20 @@ use num::{traits::FloatConst, Complex, Float};
 use std::{fmt::Debug, iter::Sum};
 
 use crate::{
-    constants::light_speed,
+    cfg::{BroadbandCfg, NoiseCfg},
+    constants::{earth_rotation_rate, light_speed},
+    coords::radec2azze,
+    error::LdsError,
+    sample::Sample,
+    spectrum::{shaping_filter, FirFilter, Spectrum},
     station::Station,
     utils::{angle2xyz, dot},
 };
-use rsdsp::{frac_delayer::FracDelayer, oscillator::COscillator};
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::StandardNormal;
+use rustfft::FftNum;
+
+use rsdsp::{cfg::DelayerCfg, frac_delayer::FracDelayer, oscillator::COscillator};
 
 pub trait StationSrc<R, T>
 where
@@ -49,17 +58,22 @@ where
         }
     }
 
-    pub fn with_delay(mut self, delay: &[T])->Self{
+    pub fn with_delay(mut self, delay: &[T]) -> Self {
         self.intrinsic_delay_pt.copy_from_slice(delay);
         self
     }
+
+    pub fn set_dir(&mut self, az: T, ze: T) {
+        self.src_dir = angle2xyz(az, ze);
+    }
 }
 
-impl<T> StationSrc<Complex<T>, T> for SingleTone<T>
+impl<R, T> StationSrc<R, T> for SingleTone<T>
 where
     T: Debug + Float + std::iter::Sum,
+    R: Debug + Sample<T>,
 {
-    fn get_sig(&mut self, station: &Station<Complex<T>, T>) -> Vec<Vec<Complex<T>>> {
+    fn get_sig(&mut self, station: &Station<R, T>) -> Vec<Vec<R>> {
         let signal: Vec<_> = (0..self.sig_len).map(|_| self.osc.get()).collect();
         station
             .ants
@@ -68,7 +82,10 @@ where
             .map(|(a, &d)| {
                 let nx = dot(&a.pos, &self.src_dir) / light_speed() / station.dt;
                 let phase_factor = Complex::<T>::new(T::zero(), (nx - d) * self.osc.dphi_dpt).exp();
-                signal.iter().map(|&x| phase_factor * x).collect::<Vec<_>>()
+                signal
+                    .iter()
+                    .map(|&x| R::from_complex(phase_factor * x))
+                    .collect::<Vec<_>>()
             })
             .collect()
     }
@@ -115,18 +132,27 @@ where
         + Send,
 {
     pub fn new(station: &Station<R, T>, az: T, ze: T, max_delay: usize, half_tap: usize) -> Self {
-        let src_dir = angle2xyz(az, ze);
-        let delays = station
+        let delayers = station
             .ants
             .iter()
-            .map(|a| -dot(&a.pos, &src_dir) / light_speed() / station.dt)
+            .map(|_| FracDelayer::new(max_delay, half_tap))
             .collect();
-        let delayers = station
+        let mut result = Self {
+            delayers,
+            delays: vec![],
+        };
+        result.set_dir(station, az, ze);
+        result
+    }
+
+    /// The delayers keep their history, so the signal stays continuous
+    pub fn set_dir(&mut self, station: &Station<R, T>, az: T, ze: T) {
+        let src_dir = angle2xyz(az, ze);
+        self.delays = station
             .ants
             .iter()
-            .map(|_| FracDelayer::new(max_delay, half_tap))
+            .map(|a| -dot(&a.pos, &src_dir) / light_speed() / station.dt)
             .collect();
-        Self { delayers, delays }
     }
 
     pub fn build(&mut self, sig: &[R]) -> GeneralSrc<R> {
@@ -149,3 +175,483 @@ where
         self.signal.clone()
     }
 }
+
+/// A source whose direction can be changed between blocks, angles in radians
+pub trait SteerableSrc<R, T>: StationSrc<R, T>
+where
+    T: std::fmt::Debug + Float,
+    R: std::fmt::Debug,
+{
+    fn set_dir(&mut self, station: &Station<R, T>, az: T, ze: T);
+}
+
+impl<R, T> SteerableSrc<R, T> for SingleTone<T>
+where
+    T: Debug + Float + FloatConst + std::iter::Sum,
+    R: Debug + Sample<T>,
+{
+    fn set_dir(&mut self, _: &Station<R, T>, az: T, ze: T) {
+        SingleTone::set_dir(self, az, ze);
+    }
+}
+
+/// Points `src` to `dir(i)` before its `i`-th block, e.g. following Earth rotation
+pub struct TrackedSrc<R, T>
+where
+    T: std::fmt::Debug + Float,
+    R: std::fmt::Debug,
+{
+    pub src: Box<dyn SteerableSrc<R, T>>,
+    pub dir: Box<dyn FnMut(usize) -> (T, T)>,
+    pub iblk: usize,
+}
+
+impl<R, T> TrackedSrc<R, T>
+where
+    T: std::fmt::Debug + Float + FloatConst + 'static,
+    R: std::fmt::Debug,
+{
+    pub fn new(src: Box<dyn SteerableSrc<R, T>>, dir: Box<dyn FnMut(usize) -> (T, T)>) -> Self {
+        TrackedSrc { src, dir, iblk: 0 }
+    }
+
+    /// Linear drift by `daz`, `dze` per block
+    pub fn drift(src: Box<dyn SteerableSrc<R, T>>, az: T, ze: T, daz: T, dze: T) -> Self {
+        Self::new(
+            src,
+            Box::new(move |i| {
+                let i = T::from(i).unwrap();
+                (az + daz * i, ze + dze * i)
+            }),
+        )
+    }
+
+    /// Sidereal motion of a fixed `ra`, `dec`, with `blk_time` seconds per block
+    pub fn sidereal(
+        src: Box<dyn SteerableSrc<R, T>>,
+        ra: T,
+        dec: T,
+        latitude: T,
+        lst0: T,
+        blk_time: T,
+    ) -> Self {
+        Self::new(
+            src,
+            Box::new(move |i| {
+                let lst = lst0 + earth_rotation_rate::<T>() * blk_time * T::from(i).unwrap();
+                radec2azze(ra, dec, lst, latitude)
+            }),
+        )
+    }
+}
+
+impl<R, T> StationSrc<R, T> for TrackedSrc<R, T>
+where
+    T: std::fmt::Debug + Float,
+    R: std::fmt::Debug,
+{
+    fn get_sig(&mut self, station: &Station<R, T>) -> Vec<Vec<R>> {
+        let (az, ze) = (self.dir)(self.iblk);
+        self.iblk += 1;
+        self.src.set_dir(station, az, ze);
+        self.src.get_sig(station)
+    }
+}
+
+pub struct NoiseSrc<R, T>
+where
+    R: Debug,
+    T: Debug + Float,
+{
+    pub rng: StdRng,
+    pub receiver_sigma: T,
+    pub sky_sigma: T,
+    pub sig_len: usize,
+    pub sky: GeneralSrcBuilder<R, T>,
+}
+
+impl<R, T> NoiseSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        station: &Station<R, T>,
+        az: T,
+        ze: T,
+        receiver_sigma: T,
+        sky_sigma: T,
+        sig_len: usize,
+        seed: u64,
+        delayer_cfg: &DelayerCfg,
+    ) -> Self {
+        NoiseSrc {
+            rng: StdRng::seed_from_u64(seed),
+            receiver_sigma,
+            sky_sigma,
+            sig_len,
+            sky: GeneralSrcBuilder::new(
+                station,
+                az,
+                ze,
+                delayer_cfg.max_delay,
+                delayer_cfg.half_tap,
+            ),
+        }
+    }
+
+    pub fn from_cfg(
+        station: &Station<R, T>,
+        cfg: &NoiseCfg,
+        delayer_cfg: &DelayerCfg,
+        sig_len: usize,
+    ) -> Result<Self, LdsError> {
+        let (az, ze) = cfg.pos.azze(station.location.as_ref(), cfg.mjd)?;
+        let (az, ze) = (T::from(az).unwrap(), T::from(ze).unwrap());
+        Ok(Self::new(
+            station,
+            az,
+            ze,
+            T::from(cfg.receiver_sigma).unwrap(),
+            T::from(cfg.sky_sigma).unwrap(),
+            sig_len,
+            cfg.seed,
+            delayer_cfg,
+        ))
+    }
+}
+
+impl<R, T> StationSrc<R, T> for NoiseSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    fn get_sig(&mut self, _: &Station<R, T>) -> Vec<Vec<R>> {
+        let sky: Vec<_> = (0..self.sig_len)
+            .map(|_| R::normal(&mut self.rng, self.sky_sigma))
+            .collect();
+        let mut signal = self.sky.build(&sky).signal;
+        for s in signal.iter_mut() {
+            for x in s.iter_mut() {
+                *x += R::normal(&mut self.rng, self.receiver_sigma);
+            }
+        }
+        signal
+    }
+}
+
+pub struct CompositeSrc<R, T>
+where
+    R: Debug,
+    T: Debug + Float,
+{
+    pub srcs: Vec<(T, Box<dyn StationSrc<R, T>>)>,
+}
+
+impl<R, T> Default for CompositeSrc<R, T>
+where
+    R: Debug,
+    T: Debug + Float,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, T> CompositeSrc<R, T>
+where
+    R: Debug,
+    T: Debug + Float,
+{
+    pub fn new() -> Self {
+        CompositeSrc { srcs: vec![] }
+    }
+
+    pub fn with_src(mut self, src: Box<dyn StationSrc<R, T>>, ampl: T) -> Self {
+        self.srcs.push((ampl, src));
+        self
+    }
+}
+
+impl<R, T> StationSrc<R, T> for CompositeSrc<R, T>
+where
+    R: Debug + Copy + std::ops::Mul<T, Output = R> + std::ops::AddAssign,
+    T: Debug + Float,
+{
+    fn get_sig(&mut self, station: &Station<R, T>) -> Vec<Vec<R>> {
+        let mut result: Option<Vec<Vec<R>>> = None;
+        for (i, (ampl, src)) in self.srcs.iter_mut().enumerate() {
+            let signal = src.get_sig(station);
+            match result.as_mut() {
+                None => {
+                    result = Some(
+                        signal
+                            .into_iter()
+                            .map(|s| s.into_iter().map(|x| x * *ampl).collect())
+                            .collect(),
+                    );
+                }
+                Some(result) => {
+                    assert_eq!(
+                        result.len(),
+                        signal.len(),
+                        "source {} provides a different number of antennas",
+                        i
+                    );
+                    for (r, s) in result.iter_mut().zip(signal.iter()) {
+                        assert_eq!(r.len(), s.len(), "source {} has a different length", i);
+                        for (r1, &s1) in r.iter_mut().zip(s.iter()) {
+                            *r1 += s1 * *ampl;
+                        }
+                    }
+                }
+            }
+        }
+        result.expect("CompositeSrc has no source")
+    }
+}
+
+pub struct BroadbandSrc<R, T>
+where
+    R: Debug,
+    T: Debug + Float,
+{
+    pub rng: StdRng,
+    pub sig_len: usize,
+    pub shaper: FirFilter<T>,
+    pub sky: GeneralSrcBuilder<R, T>,
+}
+
+impl<R, T> BroadbandSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + FftNum,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        station: &Station<R, T>,
+        spectrum: &Spectrum,
+        az: T,
+        ze: T,
+        ntap: usize,
+        sig_len: usize,
+        seed: u64,
+        delayer_cfg: &DelayerCfg,
+    ) -> Result<Self, LdsError> {
+        if spectrum.requires_sky_freq() && station.sky_freq.is_none() {
+            return Err(LdsError::NoSkyFreq);
+        }
+        if ntap == 0 {
+            return Err(LdsError::ZeroNtap);
+        }
+        // a real signal needs an even spectrum, hence a real shaping filter
+        let coeff = shaping_filter(spectrum, ntap, |f: T| {
+            let f = if R::IS_REAL { f.abs() } else { f };
+            match &station.sky_freq {
+                Some(sky_freq) => sky_freq.freq_in_hz(f, station.dt),
+                None => f,
+            }
+        });
+        Ok(BroadbandSrc {
+            rng: StdRng::seed_from_u64(seed),
+            sig_len,
+            shaper: FirFilter::new(coeff),
+            sky: GeneralSrcBuilder::new(
+                station,
+                az,
+                ze,
+                delayer_cfg.max_delay,
+                delayer_cfg.half_tap,
+            ),
+        })
+    }
+
+    pub fn from_cfg(
+        station: &Station<R, T>,
+        cfg: &BroadbandCfg,
+        delayer_cfg: &DelayerCfg,
+        sig_len: usize,
+    ) -> Result<Self, LdsError> {
+        let (az, ze) = cfg.pos.azze(station.location.as_ref(), cfg.mjd)?;
+        let (az, ze) = (T::from(az).unwrap(), T::from(ze).unwrap());
+        Self::new(
+            station,
+            &Spectrum::from_cfg(&cfg.spectrum)?,
+            az,
+            ze,
+            cfg.ntap,
+            sig_len,
+            cfg.seed,
+            delayer_cfg,
+        )
+    }
+}
+
+impl<R, T> StationSrc<R, T> for BroadbandSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    Complex<T>: From<R>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    fn get_sig(&mut self, _: &Station<R, T>) -> Vec<Vec<R>> {
+        let white: Vec<_> = (0..self.sig_len)
+            .map(|_| R::normal(&mut self.rng, T::one()).into())
+            .collect();
+        let shaped: Vec<_> = self
+            .shaper
+            .filter(&white)
+            .into_iter()
+            .map(R::from_complex)
+            .collect();
+        self.sky.build(&shaped).signal
+    }
+}
+
+impl<R, T> SteerableSrc<R, T> for NoiseSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    fn set_dir(&mut self, station: &Station<R, T>, az: T, ze: T) {
+        self.sky.set_dir(station, az, ze);
+    }
+}
+
+impl<R, T> SteerableSrc<R, T> for BroadbandSrc<R, T>
+where
+    T: Copy
+        + Float
+        + FloatConst
+        + std::ops::MulAssign<T>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + std::iter::Sum
+        + std::fmt::Debug
+        + Sync
+        + Send,
+    R: Copy
+        + std::ops::Add<R, Output = R>
+        + std::ops::Mul<R, Output = R>
+        + std::ops::Mul<T, Output = R>
+        + std::ops::MulAssign<R>
+        + ndarray::ScalarOperand
+        + num::traits::NumAssign
+        + Sum
+        + std::fmt::Debug
+        + Sync
+        + Send
+        + Sample<T>,
+    Complex<T>: From<R>,
+    StandardNormal: rand_distr::Distribution<T>,
+{
+    fn set_dir(&mut self, station: &Station<R, T>, az: T, ze: T) {
+        self.sky.set_dir(station, az, ze);
+    }
+}
@@ -0,0 +1,100 @@
+use ndarray::{Array2, ScalarOperand};
+
+use num::{
+    complex::Complex,
+    traits::{Float, FloatConst, NumAssign},
+};
+
+use std::{iter::Sum, ops::Mul};
+
+use rustfft::FftNum;
+
+use crate::{
+    sample::Sample,
+    station::Station,
+    station_src::StationSrc,
+    weights::{BeamWeights, DelayTracker},
+};
+
+pub enum Beamforming<T> {
+    Fixed(BeamWeights<T>),
+    Tracking(DelayTracker<T>),
+}
+
+/// Coarse and fine blocks of one `get_sig` chunk each, the channelizers keep
+/// their state between blocks so that the output is continuous
+pub struct AcquireStream<'a, R, T>
+where
+    T: std::fmt::Debug + Float,
+    R: std::fmt::Debug,
+{
+    pub station: &'a mut Station<R, T>,
+    pub src: &'a mut dyn StationSrc<R, T>,
+    pub beamforming: Beamforming<T>,
+    /// blocks left, endless if `None`
+    pub remaining: Option<usize>,
+}
+
+impl<'a, R, T> AcquireStream<'a, R, T>
+where
+    T: Float + std::fmt::Debug,
+    R: std::fmt::Debug,
+{
+    pub fn new(
+        station: &'a mut Station<R, T>,
+        src: &'a mut dyn StationSrc<R, T>,
+        beamforming: Beamforming<T>,
+    ) -> Self {
+        AcquireStream {
+            station,
+            src,
+            beamforming,
+            remaining: None,
+        }
+    }
+
+    pub fn with_nblk(mut self, nblk: usize) -> Self {
+        self.remaining = Some(nblk);
+        self
+    }
+}
+
+impl<R, T> Iterator for AcquireStream<'_, R, T>
+where
+    T: Float
+        + ScalarOperand
+        + FloatConst
+        + NumAssign
+        + std::iter::Sum
+        + std::marker::Send
+        + std::marker::Sync
+        + FftNum
+        + Default
+        + std::fmt::Debug,
+    Complex<T>: Copy + std::convert::From<R> + Sum + Default + ScalarOperand,
+    R: Copy
+        + Mul<T, Output = R>
+        + Default
+        + ScalarOperand
+        + NumAssign
+        + std::fmt::Debug
+        + Sum
+        + Sync
+        + Send
+        + Sample<T>,
+{
+    type Item = (Array2<Complex<T>>, Array2<Complex<T>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(n) = self.remaining.as_mut() {
+            if *n == 0 {
+                return None;
+            }
+            *n -= 1;
+        }
+        Some(match &mut self.beamforming {
+            Beamforming::Fixed(weights) => self.station.acquire_fine_weighted(self.src, weights),
+            Beamforming::Tracking(tracker) => self.station.acquire_fine_tracking(self.src, tracker),
+        })
+    }
+}
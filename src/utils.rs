@@ -11,7 +11,7 @@ use rustfft::FftNum;
 
 use rsdsp::utils::fftfreq;
 
-use crate::station::Station;
+use crate::{error::LdsError, sample::Sample, station::Station};
 
 pub fn apply_delay<T>(x: &mut Array2<Complex<T>>, d: T)
 where
@@ -52,7 +52,8 @@ where
         + std::fmt::Debug
         + Sum
         + Sync
-        + Send,
+        + Send
+        + Sample<T>,
 {
     let nfine_per_coarse = station.csp_pfb.nfine_per_coarse() * subdiv;
     let ncoarse_ch = station.ants[0].channelizer.nch_total();
@@ -72,6 +73,36 @@ where
     result
 }
 
+pub fn get_freq_to_sample_in_hz<R, T>(
+    station: &Station<R, T>,
+    subdiv: usize,
+) -> Result<Vec<T>, LdsError>
+where
+    T: Float
+        + ScalarOperand
+        + FloatConst
+        + NumAssign
+        + std::iter::Sum
+        + std::marker::Send
+        + std::marker::Sync
+        + FftNum
+        + Default
+        + std::fmt::Debug,
+    Complex<T>: Copy + std::convert::From<R> + Sum + Default + ScalarOperand,
+    R: Copy
+        + Mul<T, Output = R>
+        + Default
+        + ScalarOperand
+        + NumAssign
+        + std::fmt::Debug
+        + Sum
+        + Sync
+        + Send
+        + Sample<T>,
+{
+    station.freq_in_hz(&get_freq_to_sample(station, subdiv))
+}
+
 pub fn angle2xyz<T>(azimuth: T, zenith: T) -> [T; 3]
 //North is az=0
 where
@@ -0,0 +1,181 @@
+use ndarray::{Array2, ArrayView2};
+
+use num::{
+    complex::Complex,
+    traits::{Float, FloatConst},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::StandardNormal;
+
+use rsdsp::utils::fftfreq;
+
+use crate::{cfg::AntErrorCfg, error::LdsError};
+
+/// Complex beamformer weights with shape (nant, ncoarse_ch)
+#[derive(Debug, Clone)]
+pub struct BeamWeights<T> {
+    pub w: Array2<Complex<T>>,
+}
+
+impl<T> BeamWeights<T>
+where
+    T: Float + FloatConst + std::fmt::Debug,
+{
+    pub fn uniform(nant: usize, ncoarse_ch: usize) -> Self {
+        BeamWeights {
+            w: Array2::from_elem((nant, ncoarse_ch), Complex::<T>::new(T::one(), T::zero())),
+        }
+    }
+
+    /// Same phase ramp as `utils::apply_delay` for each antenna
+    pub fn from_delay(digital_delay: &[T], ncoarse_ch: usize) -> Self {
+        let two = T::one() + T::one();
+        let freqs = fftfreq::<T>(ncoarse_ch);
+        BeamWeights {
+            w: Array2::from_shape_fn((digital_delay.len(), ncoarse_ch), |(a, c)| {
+                Complex::<T>::new(T::zero(), -two * T::PI() * freqs[c] * digital_delay[a]).exp()
+            }),
+        }
+    }
+
+    /// Complex gains of the antenna errors at the coarse channel frequencies `freqs` (in fs)
+    /// of all coarse channels in fft order; with `real` samples a negative frequency channel
+    /// takes the conjugate gain of its positive mirror
+    pub fn from_ant_errors(cfg: &AntErrorCfg, nant: usize, freqs: &[T], real: bool) -> Self {
+        let mut delay = vec![0.0; nant];
+        let mut gain = vec![1.0; nant];
+        let mut phase = vec![0.0; nant];
+        let mut bandpass = vec![vec![1.0; freqs.len()]; nant];
+        for (x, &x1) in delay.iter_mut().zip(cfg.delay.iter()) {
+            *x = x1;
+        }
+        for (x, &x1) in gain.iter_mut().zip(cfg.gain.iter()) {
+            *x = x1;
+        }
+        for (x, &x1) in phase.iter_mut().zip(cfg.phase.iter()) {
+            *x = x1.to_radians();
+        }
+        for (x, x1) in bandpass.iter_mut().zip(cfg.bandpass.iter()) {
+            x.clone_from(x1);
+        }
+
+        if let Some(random) = &cfg.random {
+            let mut rng = StdRng::seed_from_u64(random.seed);
+            for a in 0..nant {
+                delay[a] += random.delay_sigma * rng.sample::<f64, _>(StandardNormal);
+                gain[a] *= 1.0 + random.gain_sigma * rng.sample::<f64, _>(StandardNormal);
+                phase[a] += random.phase_sigma.to_radians() * rng.sample::<f64, _>(StandardNormal);
+                for b in bandpass[a].iter_mut() {
+                    *b *= 1.0 + random.bandpass_sigma * rng.sample::<f64, _>(StandardNormal);
+                }
+            }
+        }
+
+        let two_pi = 2.0 * std::f64::consts::PI;
+        let nch = freqs.len();
+        BeamWeights {
+            w: Array2::from_shape_fn((nant, nch), |(a, c)| {
+                let f = freqs[c].to_f64().unwrap();
+                let mirrored = real && f < 0.0;
+                let (c, f) = if mirrored { (nch - c, -f) } else { (c, f) };
+                let g = Complex::<f64>::from_polar(
+                    gain[a] * bandpass[a][c],
+                    phase[a] - two_pi * f * delay[a],
+                );
+                let g = if mirrored { g.conj() } else { g };
+                Complex::<T>::new(T::from(g.re).unwrap(), T::from(g.im).unwrap())
+            }),
+        }
+    }
+
+    pub fn nant(&self) -> usize {
+        self.w.nrows()
+    }
+
+    pub fn ncoarse_ch(&self) -> usize {
+        self.w.ncols()
+    }
+
+    pub fn with_taper(mut self, taper: &[T]) -> Result<Self, LdsError> {
+        if taper.len() != self.nant() {
+            return Err(LdsError::WrongAntCount {
+                what: "taper",
+                len: taper.len(),
+                nant: self.nant(),
+            });
+        }
+        for (mut row, &t) in self.w.rows_mut().into_iter().zip(taper.iter()) {
+            row.map_inplace(|x| *x = *x * t);
+        }
+        Ok(self)
+    }
+
+    pub fn with_flagged(mut self, flagged: &[usize]) -> Result<Self, LdsError> {
+        let nant = self.nant();
+        if let Some(&ant) = flagged.iter().find(|&&a| a >= nant) {
+            return Err(LdsError::AntOutOfRange { ant, nant });
+        }
+        for &a in flagged {
+            self.w
+                .row_mut(a)
+                .fill(Complex::<T>::new(T::zero(), T::zero()));
+        }
+        Ok(self)
+    }
+
+    pub fn with_gains(mut self, gains: ArrayView2<Complex<T>>) -> Self {
+        assert_eq!(gains.shape(), self.w.shape());
+        self.w.zip_mut_with(&gains, |x, &g| *x = *x * g);
+        self
+    }
+
+    pub fn apply(&self, ant: usize, x: &mut Array2<Complex<T>>) {
+        assert_eq!(x.nrows(), self.ncoarse_ch());
+        for (mut r, &k) in x.rows_mut().into_iter().zip(self.w.row(ant).iter()) {
+            r.map_inplace(|x1| *x1 = *x1 * k);
+        }
+    }
+}
+
+/// Beamformer delays re-evaluated every `update_every` coarse samples,
+/// `delay_of` gets the coarse sample index since the tracker was created
+pub struct DelayTracker<T> {
+    pub update_every: usize,
+    pub t: usize,
+    pub delay: Vec<T>,
+    pub delay_of: Box<dyn FnMut(usize) -> Vec<T>>,
+}
+
+impl<T> DelayTracker<T>
+where
+    T: Float + FloatConst + std::fmt::Debug,
+{
+    pub fn new(
+        update_every: usize,
+        mut delay_of: Box<dyn FnMut(usize) -> Vec<T>>,
+    ) -> Result<Self, LdsError> {
+        if update_every == 0 {
+            return Err(LdsError::ZeroUpdateInterval);
+        }
+        Ok(DelayTracker {
+            update_every,
+            t: 0,
+            delay: delay_of(0),
+            delay_of,
+        })
+    }
+
+    /// Number of coarse samples for which the current delay still holds
+    pub fn remaining(&self) -> usize {
+        self.update_every - self.t % self.update_every
+    }
+
+    pub fn advance(&mut self, n: usize) {
+        assert!(n <= self.remaining());
+        self.t += n;
+        if self.t.is_multiple_of(self.update_every) {
+            self.delay = (self.delay_of)(self.t);
+        }
+    }
+}
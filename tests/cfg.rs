@@ -0,0 +1,295 @@
+use lds::{
+    cfg::{AntErrorCfg, LocationCfg, NoiseCfg, RfiCfg, SkyFreqCfg, SkyPos, StationCfg},
+    coords::{lst, radec2azze},
+    error::LdsError,
+    station::Station,
+    weights::BeamWeights,
+};
+
+use num::complex::Complex;
+
+mod common;
+
+use common::wide_cfg;
+
+#[test]
+fn validate_reports_every_issue() {
+    let mut cfg = wide_cfg("[[4, 8]]");
+    cfg.dt = f64::INFINITY;
+    cfg.pos.push(cfg.pos[0]);
+    match cfg.validate() {
+        Err(LdsError::InvalidCfg(issues)) => {
+            assert_eq!(issues.len(), 2, "{:?}", issues);
+            assert!(issues
+                .iter()
+                .any(|e| matches!(e, LdsError::NonPositiveDt(_))));
+            assert!(issues
+                .iter()
+                .any(|e| matches!(e, LdsError::DuplicatePos(0, 3))));
+        }
+        r => panic!("{:?}", r),
+    }
+}
+
+#[test]
+fn load_reports_missing_file() {
+    let err = StationCfg::load("does/not/exist.yaml").unwrap_err();
+    assert!(matches!(err, LdsError::Io { .. }));
+    assert!(err.to_string().contains("does/not/exist.yaml"));
+}
+
+#[test]
+fn single_range() {
+    let cfg = wide_cfg("[[4, 8]]");
+    assert_eq!(cfg.coarse_ch_selected(), vec![4, 5, 6, 7]);
+    assert_eq!(cfg.nselected_coarse_ch(), 4);
+    assert_eq!(cfg.total_nfine_ch(), 4 * 16);
+}
+
+#[test]
+fn multiple_ranges_are_concatenated() {
+    let cfg = wide_cfg("[[4, 8], [10, 13], [60, 62]]");
+    assert_eq!(
+        cfg.coarse_ch_selected(),
+        vec![4, 5, 6, 7, 10, 11, 12, 60, 61]
+    );
+    assert_eq!(cfg.nselected_coarse_ch(), 9);
+    assert_eq!(cfg.total_nfine_ch(), 9 * 16);
+}
+
+#[test]
+fn single_channel_ranges() {
+    let cfg = wide_cfg("[[1, 2], [3, 4]]");
+    assert_eq!(cfg.coarse_ch_selected(), vec![1, 3]);
+    assert_eq!(cfg.nselected_coarse_ch(), 2);
+}
+
+#[test]
+fn validate_multiple_ranges() {
+    assert!(wide_cfg("[[4, 8], [10, 13], [60, 62]]").validate().is_ok());
+    assert!(wide_cfg("[[4, 8], [7, 13]]").validate().is_err());
+    assert!(wide_cfg("[[8, 4]]").validate().is_err());
+    assert!(wide_cfg("[[60, 65]]").validate().is_err());
+}
+
+#[test]
+fn station_agrees_with_cfg() {
+    let cfg = wide_cfg("[[4, 8], [10, 13], [60, 62]]");
+    let station = Station::<Complex<f64>, f64>::from_cfg(&cfg);
+    assert_eq!(station.csp_pfb.coarse_ch_selected, cfg.coarse_ch_selected());
+    assert_eq!(station.fine_ch_freq_in_fs().len(), cfg.total_nfine_ch());
+    assert_eq!(
+        station.coarse_freq_of_fine_ch_in_fs().len(),
+        cfg.total_nfine_ch()
+    );
+}
+
+#[test]
+fn sky_freq_mapping() {
+    let sky_freq: SkyFreqCfg = serde_yaml::from_str("nyquist_zone: 1").unwrap();
+    let dt = 1.25e-9_f64;
+    assert!((sky_freq.freq_in_hz(1.0 / 1024.0, dt) - 781.25e3).abs() < 1e-3);
+
+    let sky_freq: SkyFreqCfg = serde_yaml::from_str("nyquist_zone: 2\nlo_freq: 1.0e6").unwrap();
+    assert!((sky_freq.freq_in_hz(0.0, dt) - 801.0e6).abs() < 1e-3);
+    assert!((sky_freq.freq_in_hz(0.25, dt) - 601.0e6).abs() < 1e-3);
+
+    let mut cfg = wide_cfg("[[4, 8]]");
+    assert!(cfg.validate().is_ok());
+    cfg.sky_freq = Some(SkyFreqCfg {
+        nyquist_zone: 0,
+        lo_freq: 0.0,
+    });
+    assert!(cfg.validate().is_err());
+}
+
+#[test]
+fn beam_selection() {
+    let mut cfg = wide_cfg("[[4, 8]]");
+    cfg.beams = serde_yaml::from_str(
+        r#"
+- az: 0.0
+  ze: 10.0
+  selected_coarse_ch: [[4, 6], [10, 12]]
+- az: 90.0
+  ze: 20.0
+  selected_coarse_ch: [[20, 21]]
+"#,
+    )
+    .unwrap();
+    assert!(cfg.validate().is_ok());
+    assert_eq!(cfg.beams[0].coarse_ch_selected(), vec![4, 5, 10, 11]);
+    assert_eq!(cfg.beams[1].coarse_ch_selected(), vec![20]);
+
+    cfg.beams[1].selected_coarse_ch = vec![(20, 70)];
+    assert!(cfg.validate().is_err());
+}
+
+#[test]
+fn pointing_in_either_frame() {
+    let noise: NoiseCfg =
+        serde_yaml::from_str("seed: 1\nreceiver_sigma: 1.0\nsky_sigma: 1.0").unwrap();
+    assert_eq!(noise.pos, SkyPos::AzZe { az: 0.0, ze: 0.0 });
+
+    let rfi: RfiCfg =
+        serde_yaml::from_str("{type: chirp, ra: 30.0, dec: -20.0, mjd: 60000.0, freq: 0.1}")
+            .unwrap();
+    assert!(matches!(
+        rfi,
+        RfiCfg::Chirp {
+            pos: SkyPos::RaDec {
+                ra: 30.0,
+                dec: -20.0
+            },
+            mjd: Some(_),
+            ..
+        }
+    ));
+
+    let mut cfg = wide_cfg("[[4, 8]]");
+    cfg.beams = serde_yaml::from_str(
+        r#"
+- {az: 90.0, ze: 20.0, selected_coarse_ch: [[4, 6]]}
+- {ra: 30.0, dec: -20.0, selected_coarse_ch: [[6, 8]]}
+"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        Station::<Complex<f64>, f64>::try_from_cfg(&cfg),
+        Err(LdsError::NoLocation)
+    ));
+    let location = LocationCfg {
+        latitude: -26.7,
+        longitude: 116.67,
+    };
+    cfg.location = Some(location);
+    assert!(matches!(
+        Station::<Complex<f64>, f64>::try_from_cfg(&cfg),
+        Err(LdsError::NoMjd)
+    ));
+    cfg.mjd = Some(60000.0);
+    let station = Station::<Complex<f64>, f64>::try_from_cfg(&cfg).unwrap();
+
+    let (az, ze) = (90.0_f64.to_radians(), 20.0_f64.to_radians());
+    let (ra_az, ra_ze) = radec2azze(
+        30.0_f64.to_radians(),
+        (-20.0_f64).to_radians(),
+        lst(60000.0, location.longitude.to_radians()),
+        location.latitude.to_radians(),
+    );
+    assert_eq!(station.beam_pointings, vec![(az, ze), (ra_az, ra_ze)]);
+}
+
+#[test]
+fn requant_gain_lengths() {
+    let mut cfg = wide_cfg("[[4, 8]]");
+    cfg.requant = serde_yaml::from_str(
+        r#"
+coarse:
+  nbits: 8
+  rounding: even
+fine:
+  nbits: 16
+  lsb: 0.5
+"#,
+    )
+    .unwrap();
+    assert!(cfg.validate().is_ok());
+
+    cfg.requant.fine.as_mut().unwrap().gain = vec![1.0; 4 * 16];
+    assert!(cfg.validate().is_ok());
+    cfg.requant.fine.as_mut().unwrap().gain = vec![1.0; 32];
+    assert!(cfg.validate().is_err());
+
+    // configured beams carry their own fine gains, sized by their own selection
+    cfg.requant.fine.as_mut().unwrap().gain = vec![];
+    cfg.beams = serde_yaml::from_str(
+        r#"
+- {az: 0.0, ze: 0.0, selected_coarse_ch: [[4, 5]], fine_gain: []}
+- {az: 0.0, ze: 0.0, selected_coarse_ch: [[5, 8]]}
+"#,
+    )
+    .unwrap();
+    cfg.beams[0].fine_gain = vec![2.0; 16];
+    cfg.beams[1].fine_gain = vec![0.5; 3 * 16];
+    assert!(cfg.validate().is_ok());
+    cfg.beams[1].fine_gain = vec![0.5; 4 * 16];
+    match cfg.validate() {
+        Err(LdsError::InvalidCfg(issues)) => {
+            assert!(matches!(
+                issues[..],
+                [LdsError::InvalidBeam { beam: 1, .. }]
+            ))
+        }
+        r => panic!("{:?}", r),
+    }
+}
+
+#[test]
+fn ant_error_lengths() {
+    let mut cfg = wide_cfg("[[4, 8]]");
+    cfg.ant_errors = serde_yaml::from_str(
+        r#"
+delay: [0.0, 0.5, -0.5]
+phase: [0.0, 10.0, 20.0]
+random:
+  seed: 1
+  gain_sigma: 0.1
+"#,
+    )
+    .unwrap();
+    assert!(cfg.validate().is_ok());
+
+    cfg.ant_errors.as_mut().unwrap().gain = vec![1.0; 2];
+    assert!(cfg.validate().is_err());
+    cfg.ant_errors.as_mut().unwrap().gain = vec![];
+    cfg.ant_errors.as_mut().unwrap().bandpass = vec![vec![1.0; 32]; 3];
+    assert!(cfg.validate().is_err());
+    cfg.ant_errors.as_mut().unwrap().bandpass = vec![vec![1.0; 64]; 3];
+    assert!(cfg.validate().is_ok());
+}
+
+#[test]
+fn ant_error_weights() {
+    let cfg: AntErrorCfg = serde_yaml::from_str(
+        r#"
+gain: [1.0, 2.0]
+phase: [0.0, 90.0]
+random:
+  seed: 3
+  delay_sigma: 0.2
+  phase_sigma: 5.0
+"#,
+    )
+    .unwrap();
+    let freqs = [0.0, 0.25, -0.25];
+    let w1 = BeamWeights::<f64>::from_ant_errors(&cfg, 2, &freqs, true);
+    let w2 = BeamWeights::<f64>::from_ant_errors(&cfg, 2, &freqs, true);
+    assert_eq!(w1.w, w2.w);
+    assert!((w1.w[(1, 0)].norm() - 2.0).abs() < 1e-12);
+    // real samples see conjugate gains on the mirrored channel
+    assert!((w1.w[(1, 1)] - w1.w[(1, 2)].conj()).norm() < 1e-12);
+
+    // including the random bandpass, drawn once per sky frequency
+    let mut cfg = cfg;
+    cfg.random.as_mut().unwrap().bandpass_sigma = 0.2;
+    let ncoarse = 8;
+    let freqs: Vec<_> = (0..ncoarse)
+        .map(|c| {
+            if c < ncoarse / 2 {
+                c as f64 / ncoarse as f64
+            } else {
+                (c as f64 - ncoarse as f64) / ncoarse as f64
+            }
+        })
+        .collect();
+    let w = BeamWeights::<f64>::from_ant_errors(&cfg, 2, &freqs, true);
+    for a in 0..2 {
+        // the Nyquist channel is its own mirror
+        for c in (1..ncoarse).filter(|&c| c != ncoarse / 2) {
+            assert!((w.w[(a, c)] - w.w[(a, ncoarse - c)].conj()).norm() < 1e-12);
+        }
+    }
+    let w = BeamWeights::<f64>::from_ant_errors(&cfg, 2, &freqs, false);
+    assert!((w.w[(1, 1)].norm() - w.w[(1, ncoarse - 1)].norm()).abs() > 1e-6);
+}
@@ -0,0 +1,37 @@
+#![allow(dead_code)]
+
+use lds::cfg::StationCfg;
+
+const SMALL_CFG: &str = r#"
+dt: 1.25e-9
+pos:
+  - [0.0, 0.0, 0.0]
+  - [1.0, 0.0, 0.0]
+  - [0.0, 1.0, 0.0]
+coarse_pfb:
+  nch: 16
+  k: 1.1
+  tap_per_ch: 4
+delayer:
+  max_delay: 16
+  half_tap: 8
+selected_coarse_ch: [[2, 5]]
+fine_pfb:
+  nch: 4
+  k: 1.1
+  tap_per_ch: 4
+"#;
+
+/// 3 antennas, 16 coarse channels of which 2..5 are selected, 4 fine channels
+pub fn small_cfg() -> StationCfg {
+    serde_yaml::from_str(SMALL_CFG).unwrap()
+}
+
+/// 3 antennas, 64 coarse and 16 fine channels with the given coarse selection
+pub fn wide_cfg(selected: &str) -> StationCfg {
+    let mut cfg = small_cfg();
+    cfg.coarse_pfb = serde_yaml::from_str("{nch: 64, k: 1.1, tap_per_ch: 8}").unwrap();
+    cfg.fine_pfb = serde_yaml::from_str("{nch: 16, k: 1.1, tap_per_ch: 8}").unwrap();
+    cfg.selected_coarse_ch = serde_yaml::from_str(selected).unwrap();
+    cfg
+}
@@ -0,0 +1,31 @@
+use lds::coords::{azze2radec, gmst, lst, radec2azze, unix_to_mjd, MJD_J2000};
+
+#[test]
+fn gmst_at_j2000() {
+    assert!((gmst(MJD_J2000).to_degrees() - 280.46061837).abs() < 1e-6);
+}
+
+#[test]
+fn lst_adds_longitude() {
+    let mjd = unix_to_mjd(1.7e9);
+    let lon = 116.67_f64.to_radians();
+    let diff = (lst(mjd, lon) - gmst(mjd) - lon).rem_euclid(2.0 * std::f64::consts::PI);
+    assert!(diff < 1e-9 || 2.0 * std::f64::consts::PI - diff < 1e-9);
+}
+
+#[test]
+fn transit_at_zenith() {
+    let lat = (-26.7_f64).to_radians();
+    let (_, ze) = radec2azze(1.0, lat, 1.0, lat);
+    assert!(ze.abs() < 1e-6);
+}
+
+#[test]
+fn radec_round_trip() {
+    let lat = 42.0_f64.to_radians();
+    let (ra, dec, lst) = (0.3_f64, 0.5_f64, 1.2_f64);
+    let (az, ze) = radec2azze(ra, dec, lst, lat);
+    let (ra1, dec1) = azze2radec(az, ze, lst, lat);
+    assert!((ra1 - ra).abs() < 1e-9);
+    assert!((dec1 - dec).abs() < 1e-9);
+}
@@ -0,0 +1,73 @@
+use lds::{
+    digitizer::Digitizer,
+    station::Station,
+    station_src::{CompositeSrc, SingleTone},
+};
+
+use num::complex::Complex;
+
+mod common;
+
+use common::small_cfg;
+
+#[test]
+fn quantization_levels() {
+    let d = Digitizer::<f64>::new(3, 0.5);
+    assert_eq!(d.max_level, 3.0);
+    for (x, q) in [
+        (0.0, 0.0),
+        (0.24, 0.0),
+        (0.25, 0.5),
+        (0.74, 0.5),
+        (0.76, 1.0),
+        (-0.25, -0.5),
+        (-1.2, -1.0),
+    ] {
+        assert_eq!(d.quantize(x), (q, false));
+    }
+}
+
+#[test]
+fn saturation() {
+    let d = Digitizer::<f64>::new(3, 0.5);
+    assert_eq!(d.quantize(1.5), (1.5, false));
+    assert_eq!(d.quantize(1.75), (1.5, true));
+    assert_eq!(d.quantize(10.0), (1.5, true));
+    assert_eq!(d.quantize(-10.0), (-1.5, true));
+}
+
+#[test]
+fn clip_statistics() {
+    let d = Digitizer::<f64>::new(3, 0.5);
+    let mut s = vec![Complex::new(10.0, 0.2), Complex::new(-0.6, -3.0)];
+    let block = d.digitize(&mut s);
+    assert_eq!(s, vec![Complex::new(1.5, 0.0), Complex::new(-0.5, -1.5)]);
+    assert_eq!(block.nsamples, 4);
+    assert_eq!(block.nclipped, 2);
+    assert!((block.signal_power - (100.0 + 0.04 + 0.36 + 9.0)).abs() < 1e-12);
+    assert!((block.error_power - (8.5 * 8.5 + 0.04 + 0.01 + 2.25)).abs() < 1e-12);
+
+    let mut s = vec![10.0, 0.3];
+    let block = d.digitize(&mut s);
+    assert_eq!((block.nsamples, block.nclipped), (2, 1));
+}
+
+#[test]
+fn station_digitizes_every_antenna() {
+    let mut cfg = small_cfg();
+    cfg.digitizer = serde_yaml::from_str("{nbits: 3, rms: 1.0, rms_lsb: 2.0}").unwrap();
+    let mut station = Station::<Complex<f64>, f64>::from_cfg(&cfg);
+    let omega = 2.0 * std::f64::consts::PI * 0.2;
+    let delay = vec![0.0; cfg.pos.len()];
+
+    // a unit tone never exceeds 1.5 rms, a 4 times louder one clips
+    for (ampl, clipped) in [(1.0, false), (4.0, true)] {
+        station.reset();
+        let tone = SingleTone::new(&station, 0.0, 0.0, omega, 1024);
+        let mut src = CompositeSrc::new().with_src(Box::new(tone), ampl);
+        station.acquire(&mut src, &delay).unwrap();
+        let stats = &station.digitizer_stats;
+        assert_eq!(stats.nsamples, vec![2048; cfg.pos.len()]);
+        assert!(stats.clip_fraction().iter().all(|&f| (f > 0.0) == clipped));
+    }
+}
@@ -0,0 +1,137 @@
+use lds::{
+    cfg::{PulsarCfg, RfiCfg, SkyModelCfg, StationCfg},
+    pulsar::PulsarSrc,
+    rfi::rfi_from_cfg,
+    sky_model::SkyModelSrc,
+    station::Station,
+    station_src::{NoiseSrc, SingleTone, StationSrc},
+};
+
+use ndarray::{Array1, Axis};
+
+mod common;
+
+const NCOARSE_CH: usize = 64;
+
+fn real_station() -> (StationCfg, Station<f64, f64>) {
+    let mut cfg = common::wide_cfg("[[4, 8], [56, 60]]");
+    cfg.sky_freq = Some(serde_yaml::from_str("nyquist_zone: 1").unwrap());
+    let station = Station::<f64, f64>::from_cfg(&cfg);
+    (cfg, station)
+}
+
+#[test]
+fn negative_channels_mirror_positive_ones() {
+    let (_, station) = real_station();
+    let pos = station.coarse_ch_freq_in_hz(&[5]).unwrap();
+    let neg = station.coarse_ch_freq_in_hz(&[NCOARSE_CH - 5]).unwrap();
+    assert!((pos[0] - neg[0]).abs() < 1e-3);
+    assert!(station.coarse_ch_freq_in_fs(&[NCOARSE_CH - 5])[0] < 0.0);
+    assert_eq!(station.positive_coarse_ch(NCOARSE_CH - 5), 5);
+    assert_eq!(station.positive_coarse_ch(NCOARSE_CH / 2), NCOARSE_CH / 2);
+}
+
+fn coarse_power(
+    station: &mut Station<f64, f64>,
+    src: &mut dyn StationSrc<f64, f64>,
+    nant: usize,
+) -> Array1<f64> {
+    let coarse = station.acquire(src, &vec![0.0; nant]).unwrap();
+    assert!(coarse.iter().all(|x| x.re.is_finite() && x.im.is_finite()));
+    coarse.map(|x| x.norm_sqr()).mean_axis(Axis(1)).unwrap()
+}
+
+#[test]
+fn rfi_tone_in_mirrored_channel() {
+    let (cfg, mut station) = real_station();
+    let ch = 5;
+    let rfi_cfg: RfiCfg = serde_yaml::from_str(&format!(
+        "{{type: chirp, az: 0.0, ze: 0.0, freq: {}}}",
+        ch as f64 / NCOARSE_CH as f64
+    ))
+    .unwrap();
+    let mut src = rfi_from_cfg(&station, &rfi_cfg, &cfg.delayer, 8192).unwrap();
+    let power = coarse_power(&mut station, src.as_mut(), cfg.pos.len());
+
+    let mirror = NCOARSE_CH - ch;
+    let peak = (0..NCOARSE_CH)
+        .max_by(|&a, &b| power[a].partial_cmp(&power[b]).unwrap())
+        .unwrap();
+    assert!(peak == ch || peak == mirror);
+    assert!((power[ch] - power[mirror]).abs() < 1e-6 * power[ch]);
+    assert_eq!(
+        station.coarse_ch_freq_in_hz(&[mirror]).unwrap(),
+        station.coarse_ch_freq_in_hz(&[ch]).unwrap()
+    );
+    let f_hz = ch as f64 / NCOARSE_CH as f64 / cfg.dt;
+    assert!((station.coarse_ch_freq_in_hz(&[mirror]).unwrap()[0] - f_hz).abs() < 1e-3);
+}
+
+#[test]
+fn real_pulsar_and_sky_model() {
+    let (cfg, mut station) = real_station();
+    let pulsar_cfg: PulsarCfg =
+        serde_yaml::from_str("{az: 0.0, ze: 10.0, dm: 1.0e-5, width: 2.5e-9, f_min: 1.0e8}")
+            .unwrap();
+    let mut src = PulsarSrc::from_cfg(&station, &pulsar_cfg, &cfg.delayer, 8192).unwrap();
+    let peak = src.template.iter().fold(0.0, |a: f64, x| a.max(x.norm()));
+    assert!(src.template.iter().all(|x| x.im.abs() <= 1e-12 * peak));
+    let power = coarse_power(&mut station, &mut src, cfg.pos.len());
+    assert!(power.sum() > 0.0);
+    for ch in 1..NCOARSE_CH / 2 {
+        assert!((power[ch] - power[NCOARSE_CH - ch]).abs() <= 1e-6 * power.sum());
+    }
+
+    let sky_cfg: SkyModelCfg = serde_yaml::from_str(
+        r#"
+seed: 2
+f_ref: 1.0e8
+sources:
+  - {az: 10.0, ze: 20.0, flux: 2.0, spectral_index: -0.7}
+ntap: 64
+"#,
+    )
+    .unwrap();
+    station.reset();
+    let mut src = SkyModelSrc::from_cfg(&station, &sky_cfg, &cfg.delayer, 8192).unwrap();
+    assert!(coarse_power(&mut station, &mut src, cfg.pos.len()).sum() > 0.0);
+}
+
+#[test]
+fn real_tone_end_to_end() {
+    let (cfg, mut station) = real_station();
+    let ch = 5;
+    let omega = 2.0 * std::f64::consts::PI * ch as f64 / NCOARSE_CH as f64;
+    let mut src = SingleTone::new(&station, 0.0, 0.0, omega, 8192);
+    let delay = vec![0.0; cfg.pos.len()];
+
+    let mut power = Array1::<f64>::zeros(NCOARSE_CH);
+    for _ in 0..4 {
+        let (coarse, fine) = station.acquire_fine(&mut src, &delay).unwrap();
+        assert_eq!(coarse.nrows(), NCOARSE_CH);
+        assert_eq!(fine.nrows(), cfg.total_nfine_ch());
+        power = power + coarse.map(|x| x.norm_sqr()).mean_axis(Axis(1)).unwrap();
+    }
+
+    let peak = (0..NCOARSE_CH)
+        .max_by(|&a, &b| power[a].partial_cmp(&power[b]).unwrap())
+        .unwrap();
+    assert!(peak == ch || peak == NCOARSE_CH - ch);
+    assert!((power[ch] - power[NCOARSE_CH - ch]).abs() < 1e-6 * power[ch]);
+}
+
+#[test]
+fn real_noise_end_to_end() {
+    let (cfg, mut station) = real_station();
+    let mut src = NoiseSrc::from_cfg(
+        &station,
+        &serde_yaml::from_str("seed: 1\nreceiver_sigma: 1.0\nsky_sigma: 1.0").unwrap(),
+        &cfg.delayer,
+        8192,
+    )
+    .unwrap();
+    let delay = vec![0.0; cfg.pos.len()];
+    let (coarse, fine) = station.acquire_fine(&mut src, &delay).unwrap();
+    assert!(coarse.iter().all(|x| x.re.is_finite() && x.im.is_finite()));
+    assert_eq!(fine.nrows(), cfg.total_nfine_ch());
+}
@@ -0,0 +1,81 @@
+use lds::{cfg::Rounding, requant::Requantizer};
+
+use ndarray::Array2;
+
+use num::complex::Complex;
+
+/// `x` is laid out as channels by samples
+fn requantize(requant: &mut Requantizer<f64>, x: Vec<Vec<Complex<f64>>>) -> Array2<Complex<f64>> {
+    let (nch, nt) = (x.len(), x[0].len());
+    let mut x = Array2::from_shape_vec((nch, nt), x.concat()).unwrap();
+    requant.apply(x.view_mut());
+    x
+}
+
+#[test]
+fn rounding_modes() {
+    let levels = [2.5, 3.5, -2.5, 1.7, -1.7];
+    for (rounding, expected) in [
+        (Rounding::Nearest, [3.0, 4.0, -3.0, 2.0, -2.0]),
+        (Rounding::Even, [2.0, 4.0, -2.0, 2.0, -2.0]),
+        (Rounding::Floor, [2.0, 3.0, -3.0, 1.0, -2.0]),
+        (Rounding::Zero, [2.0, 3.0, -2.0, 1.0, -1.0]),
+    ] {
+        let mut requant = Requantizer::new(8, 0.5, vec![], rounding);
+        let x = levels
+            .iter()
+            .map(|&l| Complex::new(l * 0.5, -l * 0.5))
+            .collect();
+        let y = requantize(&mut requant, vec![x]);
+        for ((y, e), l) in y.iter().zip(expected).zip(levels) {
+            assert_eq!(y.re, e * 0.5, "{:?}", rounding);
+            assert_eq!(y.im, rounding.round(-l) * 0.5, "{:?}", rounding);
+        }
+        assert_eq!(requant.nclipped, 0);
+    }
+}
+
+#[test]
+fn gain_scales_the_step_per_channel() {
+    let mut requant = Requantizer::new(8, 1.0, vec![1.0, 4.0], Rounding::Nearest);
+    let x = vec![Complex::new(0.3, 1.2); 2];
+    let y = requantize(&mut requant, vec![x.clone(), x]);
+    assert_eq!(y[[0, 0]], Complex::new(0.0, 1.0));
+    assert_eq!(y[[1, 0]], Complex::new(0.25, 1.25));
+}
+
+#[test]
+fn clipping_saturates_at_max_level() {
+    // 4 bits: levels -7..=7
+    let mut requant = Requantizer::new(4, 0.5, vec![], Rounding::Nearest);
+    assert_eq!(requant.max_level, 7.0);
+    let x = vec![
+        Complex::new(10.0, -10.0),
+        Complex::new(3.5, -3.5),
+        Complex::new(3.75, 0.0),
+    ];
+    let y = requantize(&mut requant, vec![x]);
+    assert_eq!(y[[0, 0]], Complex::new(3.5, -3.5));
+    assert_eq!(y[[0, 1]], Complex::new(3.5, -3.5));
+    assert_eq!(y[[0, 2]], Complex::new(3.5, 0.0));
+}
+
+#[test]
+fn clip_statistics_accumulate() {
+    let mut requant = Requantizer::new(4, 1.0, vec![], Rounding::Nearest);
+    assert_eq!(requant.clip_fraction(), 0.0);
+
+    let x = vec![Complex::new(8.0, 0.0), Complex::new(-9.0, -9.0)];
+    requantize(&mut requant, vec![x.clone(), x]);
+    // every complex sample counts as two real ones
+    assert_eq!(requant.nsamples, 8);
+    assert_eq!(requant.nclipped, 6);
+
+    requantize(&mut requant, vec![vec![Complex::new(1.0, 1.0); 4]]);
+    assert_eq!(requant.nsamples, 16);
+    assert_eq!(requant.nclipped, 6);
+    assert_eq!(requant.clip_fraction(), 6.0 / 16.0);
+
+    let requant = requant.with_zero_stats();
+    assert_eq!((requant.nsamples, requant.nclipped), (0, 0));
+}
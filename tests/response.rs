@@ -0,0 +1,46 @@
+use lds::{response::ChannelizerModel, station::Station, station_src::SingleTone};
+
+use ndarray::{Array2, Axis};
+
+use num::complex::Complex;
+
+mod common;
+
+use common::small_cfg;
+
+/// max deviation of `sim` from the best scaled `model`, relative to the peak of `sim`
+fn mismatch(sim: &Array2<f64>, model: &Array2<f64>) -> f64 {
+    let scale = (sim * model).sum() / (model * model).sum();
+    let peak = sim.fold(0.0, |a: f64, &b| a.max(b));
+    (sim - &(model * scale)).fold(0.0, |a: f64, &b| a.max(b.abs())) / peak
+}
+
+#[test]
+fn model_agrees_with_simulation() {
+    let cfg = small_cfg();
+    let station = Station::<Complex<f64>, f64>::from_cfg(&cfg);
+    let model = ChannelizerModel::from_station(&station);
+    let delay = vec![0.0; cfg.pos.len()];
+
+    // sweep across coarse channel 3 and into its neighbours
+    let freqs: Vec<f64> = (0..25)
+        .map(|i| (3.0 + (i as f64 - 12.0) / 12.0) / cfg.coarse_pfb.nch as f64)
+        .collect();
+    let mut coarse_sim = Array2::zeros((cfg.coarse_pfb.nch, freqs.len()));
+    let mut fine_sim = Array2::zeros((cfg.total_nfine_ch(), freqs.len()));
+    for (i, &f) in freqs.iter().enumerate() {
+        let mut station = station.clone();
+        let mut src = SingleTone::new(&station, 0.0, 0.0, 2.0 * std::f64::consts::PI * f, 4096);
+        station.acquire_fine(&mut src, &delay).unwrap();
+        let (coarse, fine) = station.acquire_fine(&mut src, &delay).unwrap();
+        coarse_sim
+            .column_mut(i)
+            .assign(&coarse.map(|x| x.norm_sqr()).mean_axis(Axis(1)).unwrap());
+        fine_sim
+            .column_mut(i)
+            .assign(&fine.map(|x| x.norm_sqr()).mean_axis(Axis(1)).unwrap());
+    }
+
+    assert!(mismatch(&coarse_sim, &model.coarse_resp(&freqs)) < 0.02);
+    assert!(mismatch(&fine_sim, &model.fine_resp(&freqs)) < 0.02);
+}
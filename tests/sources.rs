@@ -0,0 +1,338 @@
+use lds::{
+    cfg::{
+        BroadbandCfg, NoiseCfg, PulsarCfg, RfiCfg, SkyFreqCfg, SkyModelCfg, SpectrumCfg, StationCfg,
+    },
+    constants::{dispersion_const, light_speed},
+    error::LdsError,
+    pulsar::PulsarSrc,
+    rfi::{rfi_from_cfg, ChirpTone, MovingTone},
+    sky_model::SkyModelSrc,
+    spectrum::{shaping_filter, Spectrum},
+    station::Station,
+    station_src::{BroadbandSrc, CompositeSrc, NoiseSrc, StationSrc},
+    utils::{angle2xyz, dot},
+};
+
+use num::complex::Complex;
+
+mod common;
+
+type StationType = Station<Complex<f64>, f64>;
+
+fn small_cfg() -> StationCfg {
+    let mut cfg = common::small_cfg();
+    cfg.sky_freq = Some(serde_yaml::from_str("nyquist_zone: 1").unwrap());
+    cfg
+}
+
+fn noise_src(station: &StationType, cfg: &StationCfg, seed: u64) -> NoiseSrc<Complex<f64>, f64> {
+    let noise_cfg: NoiseCfg = serde_yaml::from_str(&format!(
+        "seed: {}\nreceiver_sigma: 1.0\nsky_sigma: 1.0\naz: 30.0\nze: 20.0",
+        seed
+    ))
+    .unwrap();
+    NoiseSrc::from_cfg(station, &noise_cfg, &cfg.delayer, 256).unwrap()
+}
+
+#[test]
+fn noise_is_reproducible_from_seed() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    let noise = |seed: u64| noise_src(&station, &cfg, seed).get_sig(&station);
+    assert_eq!(noise(7), noise(7));
+    assert_ne!(noise(7), noise(8));
+}
+
+#[test]
+fn composite_is_weighted_sum() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    let a = noise_src(&station, &cfg, 1).get_sig(&station);
+    let b = noise_src(&station, &cfg, 2).get_sig(&station);
+    let sum = CompositeSrc::new()
+        .with_src(Box::new(noise_src(&station, &cfg, 1)), 2.0)
+        .with_src(Box::new(noise_src(&station, &cfg, 2)), 0.5)
+        .get_sig(&station);
+    for ((a, b), s) in a.iter().zip(b.iter()).zip(sum.iter()) {
+        for ((&a, &b), &s) in a.iter().zip(b.iter()).zip(s.iter()) {
+            assert!((a * 2.0 + b * 0.5 - s).norm() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn power_law_filter_is_finite_at_dc() {
+    // zone 1 without LO puts bin 0 at 0 Hz
+    let sky_freq = SkyFreqCfg {
+        nyquist_zone: 1,
+        lo_freq: 0.0,
+    };
+    let spectrum = Spectrum::PowerLaw {
+        index: -0.7,
+        f_ref: 1e8,
+    };
+    let h = shaping_filter::<f64, _>(&spectrum, 64, |f| sky_freq.freq_in_hz(f, 1.25e-9));
+    assert!(h.iter().all(|x| x.re.is_finite() && x.im.is_finite()));
+    assert!(h.iter().any(|x| x.norm() > 0.0));
+}
+
+#[test]
+fn broadband_power_law_is_finite() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    let mut bb_cfg: BroadbandCfg = serde_yaml::from_str(
+        r#"
+seed: 3
+spectrum: {type: power_law, index: -0.7, f_ref: 1.0e8}
+az: 0.0
+ze: 10.0
+ntap: 64
+"#,
+    )
+    .unwrap();
+    let mut src = BroadbandSrc::from_cfg(&station, &bb_cfg, &cfg.delayer, 256).unwrap();
+    let signal = src.get_sig(&station);
+    assert!(signal
+        .iter()
+        .flatten()
+        .all(|x| x.re.is_finite() && x.im.is_finite()));
+
+    bb_cfg.ntap = 0;
+    assert!(matches!(
+        BroadbandSrc::<Complex<f64>, f64>::from_cfg(&station, &bb_cfg, &cfg.delayer, 256),
+        Err(LdsError::ZeroNtap)
+    ));
+
+    bb_cfg.ntap = 64;
+    for f_ref in [0.0, -1.0e8, f64::INFINITY, f64::NAN] {
+        bb_cfg.spectrum = SpectrumCfg::PowerLaw { index: -0.7, f_ref };
+        assert!(matches!(
+            BroadbandSrc::<Complex<f64>, f64>::from_cfg(&station, &bb_cfg, &cfg.delayer, 256),
+            Err(LdsError::InvalidRefFreq(_))
+        ));
+    }
+}
+
+fn sky_cfg(seed: u64) -> SkyModelCfg {
+    serde_yaml::from_str(&format!(
+        r#"
+seed: {}
+f_ref: 1.0e8
+observer: {{latitude: -26.7, lst: 30.0}}
+sources:
+  - {{az: 10.0, ze: 20.0, flux: 2.0, spectral_index: -0.7}}
+  - {{ra: 40.0, dec: -30.0, flux: 1.0, spectral_index: -2.5}}
+diffuse: {{total_flux: 1.0, nsrc: 4, spectral_index: -0.7}}
+ntap: 64
+"#,
+        seed
+    ))
+    .unwrap()
+}
+
+#[test]
+fn sky_model_is_finite_and_reproducible() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    let sky = |seed: u64| {
+        SkyModelSrc::from_cfg(&station, &sky_cfg(seed), &cfg.delayer, 256)
+            .unwrap()
+            .get_sig(&station)
+    };
+    let signal = sky(5);
+    assert!(signal
+        .iter()
+        .flatten()
+        .all(|x| x.re.is_finite() && x.im.is_finite()));
+    assert!(signal.iter().flatten().any(|x| x.norm() > 0.0));
+    assert_eq!(signal, sky(5));
+    assert_ne!(signal, sky(6));
+}
+
+#[test]
+fn pulsar_delay_follows_dm() {
+    let mut cfg = small_cfg();
+    cfg.pos = vec![[0.0, 0.0, 0.0]];
+    cfg.coarse_pfb.nch = 64;
+    cfg.selected_coarse_ch = vec![(16, 17)];
+    let mut station = StationType::from_cfg(&cfg);
+    let pulsar_cfg: PulsarCfg = serde_yaml::from_str(
+        r#"
+az: 0.0
+ze: 0.0
+dm: 1.0e-4
+width: 1.25e-9
+f_min: 1.5e8
+"#,
+    )
+    .unwrap();
+    let mut src = PulsarSrc::from_cfg(&station, &pulsar_cfg, &cfg.delayer, 16384).unwrap();
+    let coarse = station.acquire(&mut src, &[0.0]).unwrap();
+
+    // power-weighted arrival time in coarse samples
+    let arrival = |c: usize| {
+        let p = coarse.row(c).mapv(|x| x.norm_sqr());
+        p.iter()
+            .enumerate()
+            .map(|(t, &p1)| t as f64 * p1)
+            .sum::<f64>()
+            / p.sum()
+    };
+    let freq = station.coarse_ch_freq_in_hz(&[16, 28]).unwrap();
+    let coarse_dt = cfg.dt * station.coarse_sample_interval() as f64;
+    let expected = dispersion_const::<f64>()
+        * pulsar_cfg.dm
+        * (1.0 / (freq[0] * freq[0]) - 1.0 / (freq[1] * freq[1]))
+        / coarse_dt;
+    let measured = arrival(16) - arrival(28);
+    assert!(expected > 50.0);
+    assert!((measured - expected).abs() < 0.03 * expected);
+}
+
+#[test]
+fn pulsar_rejects_non_positive_period_and_width() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    for yaml in [
+        "{az: 0.0, ze: 0.0, dm: 0.0, width: 1.0e-8, period: 0.0}",
+        "{az: 0.0, ze: 0.0, dm: 0.0, width: 1.0e-8, period: -1.0e-6}",
+        "{az: 0.0, ze: 0.0, dm: 0.0, width: 0.0}",
+        "{az: 0.0, ze: 0.0, dm: 0.0, width: -1.0e-8, period: 1.0e-6}",
+    ] {
+        let pulsar_cfg: PulsarCfg = serde_yaml::from_str(yaml).unwrap();
+        let result = PulsarSrc::from_cfg(&station, &pulsar_cfg, &cfg.delayer, 1024);
+        assert!(
+            matches!(result, Err(LdsError::InvalidPulsar(_))),
+            "{}",
+            yaml
+        );
+    }
+    let pulsar_cfg: PulsarCfg =
+        serde_yaml::from_str("{az: 0.0, ze: 0.0, dm: 0.0, width: 1.0e-8, period: 1.0e-6}").unwrap();
+    assert!(PulsarSrc::from_cfg(&station, &pulsar_cfg, &cfg.delayer, 1024).is_ok());
+}
+
+fn coarse_power(
+    station: &mut StationType,
+    src: &mut dyn StationSrc<Complex<f64>, f64>,
+) -> Vec<f64> {
+    let delay = vec![0.0; station.ants.len()];
+    station.acquire(src, &delay).unwrap();
+    let coarse = station.acquire(src, &delay).unwrap();
+    coarse
+        .rows()
+        .into_iter()
+        .map(|r| r.iter().map(|x| x.norm_sqr()).sum())
+        .collect()
+}
+
+fn peak(power: &[f64]) -> usize {
+    (0..power.len())
+        .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+        .unwrap()
+}
+
+#[test]
+fn rfi_tones_land_in_their_channel() {
+    let cfg = small_cfg();
+    let mut station = StationType::from_cfg(&cfg);
+    for (yaml, ch) in [
+        ("{type: chirp, az: 0.0, ze: 0.0, freq: 0.3125}", 5),
+        ("{type: chirp, az: 0.0, ze: 0.0, freq: -0.1875}", 13),
+        (
+            "{type: satellite, az: 20.0, ze: 0.0, az_rate: 0.0, ze_rate: 1.0, freq: 0.125}",
+            2,
+        ),
+    ] {
+        let rfi_cfg: RfiCfg = serde_yaml::from_str(yaml).unwrap();
+        let mut src = rfi_from_cfg(&station, &rfi_cfg, &cfg.delayer, 1024).unwrap();
+        station.reset();
+        let power = coarse_power(&mut station, src.as_mut());
+        assert_eq!(peak(&power), ch, "{}", yaml);
+        let total: f64 = power.iter().sum();
+        assert!(power[ch] > 0.5 * total, "{}", yaml);
+    }
+}
+
+#[test]
+fn still_moving_tone_matches_chirp() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    let (az, ze, omega) = (0.4, 0.3, 2.0 * std::f64::consts::PI * 0.2);
+    let mut moving = MovingTone::new(&station, az, ze, 0.0, 0.0, omega, 0.5, 1024);
+    let mut chirp = ChirpTone::new(&station, az, ze, omega, 0.0, 0.5, 1024);
+    for _ in 0..2 {
+        let x = StationSrc::<Complex<f64>, f64>::get_sig(&mut moving, &station);
+        let y = StationSrc::<Complex<f64>, f64>::get_sig(&mut chirp, &station);
+        for (x, y) in x.iter().flatten().zip(y.iter().flatten()) {
+            assert!((x - y).norm() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn moving_tone_delays_change_within_a_block() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    let (az, ze, omega) = (0.0, 0.2, 2.0 * std::f64::consts::PI * 0.2);
+    let (dze_dpt, n) = (4.0e-4, 1024);
+    let mut src = MovingTone::new(&station, az, ze, 0.0, dze_dpt, omega, 1.0, n);
+    let signal = StationSrc::<Complex<f64>, f64>::get_sig(&mut src, &station);
+
+    // antenna 2 sits 1 m north, so its phase leads antenna 0 by the geometric delay of
+    // the direction at that very sample
+    let phase = |t: usize| (signal[2][t] / signal[0][t]).arg();
+    let expected = |t: usize| {
+        let dir = angle2xyz(az, ze + dze_dpt * t as f64);
+        let nx = dot(&station.ants[2].pos, &dir) / light_speed::<f64>() / cfg.dt;
+        Complex::new(0.0, nx * omega).exp().arg()
+    };
+    for t in [0, n / 2, n - 1] {
+        assert!((phase(t) - expected(t)).abs() < 1e-9, "sample {}", t);
+    }
+    assert!((phase(n - 1) - phase(0)).abs() > 0.5);
+}
+
+#[test]
+fn rfi_rejects_invalid_parameters() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    for yaml in [
+        "{type: impulsive, seed: 1, az: 0.0, ze: 0.0, rate: -1.0e7, width: 2.0e-8}",
+        "{type: impulsive, seed: 1, az: 0.0, ze: 0.0, rate: 1.0e7, width: -2.0e-8}",
+        "{type: impulsive, seed: 1, az: 0.0, ze: 0.0, rate: 1.0e7, width: 0.0}",
+        "{type: impulsive, seed: 1, az: 0.0, ze: 0.0, rate: 1.0e7, width: 2.0e-8, ampl: .nan}",
+        "{type: chirp, az: 0.0, ze: 0.0, freq: 0.1, ampl: .nan}",
+    ] {
+        let rfi_cfg: RfiCfg = serde_yaml::from_str(yaml).unwrap();
+        assert!(
+            matches!(
+                rfi_from_cfg::<Complex<f64>, f64>(&station, &rfi_cfg, &cfg.delayer, 1024),
+                Err(LdsError::InvalidRfi(_))
+            ),
+            "{}",
+            yaml
+        );
+    }
+}
+
+#[test]
+fn impulsive_rfi_is_reproducible_from_seed() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    let bursts = |seed: u64| {
+        let rfi_cfg: RfiCfg = serde_yaml::from_str(&format!(
+            "{{type: impulsive, seed: {}, az: 0.0, ze: 10.0, rate: 1.0e7, width: 2.0e-8}}",
+            seed
+        ))
+        .unwrap();
+        rfi_from_cfg(&station, &rfi_cfg, &cfg.delayer, 4096)
+            .unwrap()
+            .get_sig(&station)
+    };
+    let signal = bursts(1);
+    assert!(signal.iter().flatten().any(|x| x.norm() > 0.0));
+    assert!(signal.iter().flatten().any(|x| x.norm() == 0.0));
+    assert_eq!(signal, bursts(1));
+    assert_ne!(signal, bursts(2));
+}
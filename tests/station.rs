@@ -0,0 +1,271 @@
+use lds::{
+    error::LdsError,
+    station::Station,
+    station_src::{NoiseSrc, SingleTone},
+    stream::Beamforming,
+};
+
+use ndarray::{concatenate, Axis};
+
+use num::complex::Complex;
+
+mod common;
+
+use common::small_cfg;
+
+type StationType = Station<Complex<f64>, f64>;
+
+fn tone(station: &StationType) -> SingleTone<f64> {
+    SingleTone::new(station, 0.0, 0.3, 2.0 * std::f64::consts::PI * 0.2, 1024)
+}
+
+#[test]
+fn reset_matches_fresh_station() {
+    let mut cfg = small_cfg();
+    cfg.digitizer = serde_yaml::from_str("{nbits: 8, rms: 1.0, rms_lsb: 16.0}").unwrap();
+    let nant = cfg.pos.len();
+    let delay = vec![0.0; nant];
+    let mut fresh = StationType::from_cfg(&cfg);
+    let expected = fresh.acquire_fine(&mut tone(&fresh), &delay).unwrap();
+
+    let mut station = StationType::from_cfg(&cfg);
+    let mut src = tone(&station);
+    station.acquire_fine(&mut src, &delay).unwrap();
+    station.acquire_fine(&mut src, &delay).unwrap();
+    station.reset();
+    assert_eq!(station.digitizer_stats.nsamples, vec![0; nant]);
+    assert_eq!(station.digitizer_stats.signal_power, vec![0.0; nant]);
+    assert_eq!(
+        station.acquire_fine(&mut tone(&station), &delay).unwrap(),
+        expected
+    );
+    assert_eq!(
+        station.digitizer_stats.nsamples,
+        fresh.digitizer_stats.nsamples
+    );
+}
+
+#[test]
+fn wrong_delay_count_is_an_error() {
+    let cfg = small_cfg();
+    let mut station = StationType::from_cfg(&cfg);
+    let short = vec![0.0; cfg.pos.len() - 1];
+    assert!(matches!(
+        station.acquire(&mut tone(&station), &short),
+        Err(LdsError::WrongAntCount { what: "delay", .. })
+    ));
+    assert!(station.acquire_fine(&mut tone(&station), &short).is_err());
+    let delays = vec![vec![0.0; cfg.pos.len()], short];
+    assert!(station.acquire_multi(&mut tone(&station), &delays).is_err());
+    assert!(station
+        .acquire_fine_multi(&mut tone(&station), &delays)
+        .is_err());
+}
+
+#[test]
+fn multi_beam_matches_single_beams() {
+    let cfg = small_cfg();
+    let station = StationType::from_cfg(&cfg);
+    let delay = station.calc_required_digital_delay(0.5, 0.3);
+    let nbeams = 3;
+
+    let mut multi = station.clone();
+    let mut src = tone(&multi);
+    let mut single = station.clone();
+    let mut single_src = tone(&single);
+    for _ in 0..2 {
+        let (coarse, fine) = multi
+            .acquire_fine_multi(&mut src, &vec![delay.clone(); nbeams])
+            .unwrap();
+        let (coarse1, fine1) = single.acquire_fine(&mut single_src, &delay).unwrap();
+        assert_eq!(coarse.shape()[1..], *coarse1.shape());
+        assert_eq!(fine.shape()[1..], *fine1.shape());
+        for b in 0..nbeams {
+            for (x, y) in coarse
+                .outer_iter()
+                .nth(b)
+                .unwrap()
+                .iter()
+                .zip(coarse1.iter())
+            {
+                assert!((x - y).norm() <= 1e-12 * (1.0 + y.norm()));
+            }
+            for (x, y) in fine.outer_iter().nth(b).unwrap().iter().zip(fine1.iter()) {
+                assert!((x - y).norm() <= 1e-12 * (1.0 + y.norm()));
+            }
+        }
+    }
+}
+
+#[test]
+fn configured_beams_of_different_sizes() {
+    let mut cfg = small_cfg();
+    cfg.beams = serde_yaml::from_str(
+        r#"
+- {az: 0.0, ze: 0.0, selected_coarse_ch: [[2, 3]]}
+- {az: 90.0, ze: 20.0, selected_coarse_ch: [[5, 7]]}
+"#,
+    )
+    .unwrap();
+    cfg.validate().unwrap();
+    let mut station = StationType::from_cfg(&cfg);
+    let nfine = cfg.fine_pfb.nch;
+
+    let (coarse, fine) = station.acquire_fine_beams(&mut tone(&station));
+    assert_eq!(coarse.shape()[0], 2);
+    assert_eq!(fine[0].nrows(), nfine);
+    assert_eq!(fine[1].nrows(), 2 * nfine);
+
+    // ad-hoc beams keep the station-wide selection whatever the configured beams are
+    let delays = vec![vec![0.0; cfg.pos.len()]; 2];
+    let (_, fine) = station
+        .acquire_fine_multi(&mut tone(&station), &delays)
+        .unwrap();
+    assert_eq!(fine.shape()[..2], [2, cfg.total_nfine_ch()]);
+}
+
+#[test]
+fn configured_beams_have_own_fine_gains() {
+    let mut cfg = small_cfg();
+    cfg.requant.fine = serde_yaml::from_str("{nbits: 4, lsb: 1.0}").unwrap();
+    cfg.beams = serde_yaml::from_str(
+        r#"
+- {az: 0.0, ze: 0.0, selected_coarse_ch: [[2, 3]]}
+- {az: 0.0, ze: 0.0, selected_coarse_ch: [[2, 4]]}
+"#,
+    )
+    .unwrap();
+    let nfine = cfg.fine_pfb.nch;
+    cfg.beams[0].fine_gain = vec![1.0e6; nfine];
+    cfg.beams[1].fine_gain = vec![1.0e-6; 2 * nfine];
+    cfg.validate().unwrap();
+    let mut station = StationType::from_cfg(&cfg);
+
+    let (_, fine) = station.acquire_fine_beams(&mut tone(&station));
+    // 4 bits saturate at 7 steps of lsb / gain
+    let max = 7.0e-6 * (1.0 + 1e-12);
+    assert!(fine[0]
+        .iter()
+        .all(|x| x.re.abs() <= max && x.im.abs() <= max));
+    assert!(fine[1].iter().all(|x| x.norm() == 0.0));
+    let clipped = |b: usize| station.beam_requant[b].as_ref().unwrap().clip_fraction();
+    assert!(clipped(0) > 0.0);
+    assert_eq!(clipped(1), 0.0);
+    assert_eq!(
+        station.beam_requant[1].as_ref().unwrap().nsamples,
+        2 * fine[1].len()
+    );
+    // the station-wide fine requantizer is left to the csp path
+    assert_eq!(station.requant.fine.as_ref().unwrap().nsamples, 0);
+}
+
+#[test]
+fn reset_clears_requant_stats() {
+    let mut cfg = small_cfg();
+    cfg.requant = serde_yaml::from_str(
+        r#"
+coarse: {nbits: 8}
+beam: {nbits: 8}
+fine: {nbits: 8}
+"#,
+    )
+    .unwrap();
+    cfg.beams = serde_yaml::from_str("[{az: 0.0, ze: 0.0, selected_coarse_ch: [[2, 3]]}]").unwrap();
+    let mut station = StationType::from_cfg(&cfg);
+    let delay = vec![0.0; cfg.pos.len()];
+    station.acquire_fine(&mut tone(&station), &delay).unwrap();
+    station.acquire_fine_beams(&mut tone(&station));
+    station
+        .acquire_fine_multi(&mut tone(&station), &[delay.clone(), delay])
+        .unwrap();
+
+    let stats = |station: &StationType| {
+        let stages = &station.requant;
+        [&stages.coarse, &stages.beam, &stages.fine]
+            .into_iter()
+            .chain(station.beam_requant.iter())
+            .chain(station.multi_requant.iter())
+            .map(|r| r.as_ref().map(|r| (r.nsamples, r.nclipped)).unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(stats(&station).len(), 6);
+    assert!(stats(&station).iter().all(|&(n, _)| n > 0));
+    station.reset();
+    assert!(stats(&station).iter().all(|&n| n == (0, 0)));
+}
+
+#[test]
+fn streamed_blocks_match_whole_buffer() {
+    let cfg = small_cfg();
+    let omega = 2.0 * std::f64::consts::PI * 0.2;
+    let (nblk, blk_len) = (4, 1024);
+
+    let mut station = StationType::from_cfg(&cfg);
+    let delay = station.calc_required_digital_delay(0.5, 0.3);
+    let weights = station.delay_weights(&delay);
+    let mut src = SingleTone::new(&station, 0.5, 0.3, omega, nblk * blk_len);
+    let (coarse, fine) = station.acquire_fine_weighted(&mut src, &weights);
+
+    let mut station = StationType::from_cfg(&cfg);
+    let mut src = SingleTone::new(&station, 0.5, 0.3, omega, blk_len);
+    let blocks: Vec<_> = station
+        .stream(&mut src, Beamforming::Fixed(weights))
+        .with_nblk(nblk)
+        .collect();
+    let join = |i: usize| {
+        let views: Vec<_> = blocks
+            .iter()
+            .map(|b| if i == 0 { b.0.view() } else { b.1.view() })
+            .collect();
+        concatenate(Axis(1), &views).unwrap()
+    };
+
+    for (streamed, whole) in [(join(0), coarse), (join(1), fine)] {
+        assert_eq!(streamed.shape(), whole.shape());
+        let scale = whole.iter().fold(0.0, |a: f64, x| a.max(x.norm()));
+        assert!(scale > 0.0);
+        assert!(streamed
+            .iter()
+            .zip(whole.iter())
+            .all(|(a, b)| (a - b).norm() <= 1e-9 * scale));
+    }
+}
+
+#[test]
+fn beamforming_is_independent_of_thread_count() {
+    let mut cfg = small_cfg();
+    cfg.pos = (0..40)
+        .map(|i| [(i % 8) as f64 * 1.5, (i / 8) as f64 * 1.5, 0.0])
+        .collect();
+    let run = || {
+        let mut station = StationType::from_cfg(&cfg);
+        let mut src = NoiseSrc::from_cfg(
+            &station,
+            &serde_yaml::from_str("seed: 7\nreceiver_sigma: 1.0\nsky_sigma: 1.0\nze: 20.0")
+                .unwrap(),
+            &cfg.delayer,
+            1024,
+        )
+        .unwrap();
+        let delay = station.calc_required_digital_delay(0.5, 0.3);
+        let fixed = station.acquire_fine(&mut src, &delay).unwrap();
+        let tracker = station
+            .delay_tracker(32, Box::new(|t| (0.5 + 1e-3 * t as f64, 0.3)))
+            .unwrap();
+        let tracking = station
+            .stream(&mut src, Beamforming::Tracking(tracker))
+            .with_nblk(1)
+            .collect::<Vec<_>>();
+        (fixed, tracking)
+    };
+
+    let expected = run();
+    assert_eq!(run(), expected);
+    for nthreads in [1, 3, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(nthreads)
+            .build()
+            .unwrap();
+        assert_eq!(pool.install(run), expected);
+    }
+}
@@ -0,0 +1,77 @@
+use lds::{
+    error::LdsError,
+    weights::{BeamWeights, DelayTracker},
+};
+
+use num::complex::Complex;
+
+use std::{cell::RefCell, rc::Rc};
+
+#[test]
+fn taper_scales_rows() {
+    let w = BeamWeights::<f64>::uniform(3, 4)
+        .with_taper(&[1.0, 0.5, 0.25])
+        .unwrap();
+    for (a, t) in [1.0, 0.5, 0.25].iter().enumerate() {
+        assert!(w.w.row(a).iter().all(|x| *x == Complex::new(*t, 0.0)));
+    }
+}
+
+#[test]
+fn flagged_rows_are_zero() {
+    let w = BeamWeights::<f64>::from_delay(&[0.1, 0.2, 0.3], 4)
+        .with_flagged(&[1])
+        .unwrap();
+    assert!(w.w.row(1).iter().all(|x| x.norm() == 0.0));
+    assert!(w.w.row(0).iter().all(|x| (x.norm() - 1.0).abs() < 1e-12));
+    assert!(w.w.row(2).iter().all(|x| (x.norm() - 1.0).abs() < 1e-12));
+}
+
+#[test]
+fn wrong_taper_length_and_flag_index_are_errors() {
+    let w = BeamWeights::<f64>::uniform(3, 4);
+    assert!(matches!(
+        w.clone().with_taper(&[1.0, 1.0]),
+        Err(LdsError::WrongAntCount {
+            len: 2,
+            nant: 3,
+            ..
+        })
+    ));
+    assert!(matches!(
+        w.with_flagged(&[0, 3]),
+        Err(LdsError::AntOutOfRange { ant: 3, nant: 3 })
+    ));
+}
+
+#[test]
+fn tracker_updates_every_interval() {
+    let calls = Rc::new(RefCell::new(vec![]));
+    let log = calls.clone();
+    let mut tracker = DelayTracker::<f64>::new(
+        4,
+        Box::new(move |t| {
+            log.borrow_mut().push(t);
+            vec![t as f64]
+        }),
+    )
+    .unwrap();
+    assert_eq!(tracker.remaining(), 4);
+    for n in [3, 1, 2, 2, 4] {
+        tracker.advance(n);
+    }
+    assert_eq!(*calls.borrow(), vec![0, 4, 8, 12]);
+    assert_eq!(tracker.delay, vec![12.0]);
+
+    tracker.advance(1);
+    assert_eq!(tracker.remaining(), 3);
+    assert_eq!(tracker.delay, vec![12.0]);
+}
+
+#[test]
+fn zero_update_interval_is_an_error() {
+    assert!(matches!(
+        DelayTracker::<f64>::new(0, Box::new(|_| vec![0.0])),
+        Err(LdsError::ZeroUpdateInterval)
+    ));
+}